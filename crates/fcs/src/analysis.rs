@@ -1,5 +1,84 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
+use crate::{
+    error::Result,
+    text::{gates, parse_pairs, Gate},
+};
+
+/// Keyword/value pairs of the ANALYSIS segment.
+#[derive(Debug)]
 pub struct Analysis {
-    pub pairs: HashMap<String, String>,
+    pub pairs: BTreeMap<String, String>,
+}
+
+/// A gating region, defined over one or two parameters.
+#[derive(Debug)]
+pub struct Region {
+    /// index
+    pub index: u32,
+    /// $RnI
+    pub parameters: Option<String>,
+    /// $RnW
+    pub window: Option<String>,
+}
+
+impl Analysis {
+    pub fn new(bytes: &[u8]) -> Result<Self> {
+        Ok(Analysis {
+            pairs: parse_pairs(bytes)?,
+        })
+    }
+
+    pub fn get<K>(&self, key: K) -> Option<&String>
+    where
+        K: ToString,
+    {
+        self.pairs.get(&key.to_string())
+    }
+
+    /// $GATING: the gate expression combining the regions.
+    pub fn gating(&self) -> Option<&String> {
+        self.get("$GATING")
+    }
+
+    /// Gate parameters and their statistics.
+    pub fn gates(&self) -> Result<Vec<Gate>> {
+        gates(&self.pairs)
+    }
+
+    /// Regions, from $R1I/$R1W up to the first missing index.
+    pub fn regions(&self) -> Vec<Region> {
+        (1..)
+            .map(|index| Region {
+                index,
+                parameters: self.get(format!("$R{}I", index)).cloned(),
+                window: self.get(format!("$R{}W", index)).cloned(),
+            })
+            .take_while(|region| region.parameters.is_some() || region.window.is_some())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Analysis;
+
+    #[test]
+    fn it_parses_gating_keywords() {
+        let analysis = Analysis::new(
+            b"/$GATING/R1.AND.R2/$R1I/1/$R1W/10,200/$R2I/2/$R2W/5,50/$GATE/1/$G1N/FSC-H/$G1P/42.5/",
+        )
+        .unwrap();
+
+        assert_eq!(analysis.gating().unwrap(), "R1.AND.R2");
+
+        let regions = analysis.regions();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[1].window.as_deref(), Some("5,50"));
+
+        let gates = analysis.gates().unwrap();
+        assert_eq!(gates.len(), 1);
+        assert_eq!(gates[0].short_name.as_deref(), Some("FSC-H"));
+        assert_eq!(gates[0].percent_emitted.as_deref(), Some("42.5"));
+    }
 }
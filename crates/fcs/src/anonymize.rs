@@ -0,0 +1,248 @@
+use std::{fmt, io::Write};
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    date::Date,
+    error::{FcsError, Result},
+    text::Text,
+    traits::FcsRead,
+};
+
+/// What to do with the value of a keyword matched by a rule.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Remove the keyword.
+    Drop,
+    /// Replace the value with the hex SHA-256 of the salt and the value, so
+    /// equal values still match across files anonymized with the same salt.
+    Hash,
+    /// Move the date the value starts with by a number of days, keeping
+    /// its format and anything that follows it, such as a time.
+    ShiftDate(i64),
+}
+
+/// An action applied to every keyword matching a pattern.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    /// Keyword, compared ignoring case, where `*` matches any characters.
+    pub pattern: String,
+    pub action: Action,
+}
+
+impl Rule {
+    pub fn matches(&self, keyword: &str) -> bool {
+        glob(
+            self.pattern.to_ascii_uppercase().as_bytes(),
+            keyword.to_ascii_uppercase().as_bytes(),
+        )
+    }
+}
+
+/// A change made to TEXT, as recorded in the audit log. Original values
+/// are never recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub keyword: String,
+    pub action: Action,
+    /// The new value, or None when the keyword was dropped.
+    pub value: Option<String>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.action, &self.value) {
+            (Action::Drop, _) | (_, None) => write!(f, "{}: dropped", self.keyword),
+            (Action::Hash, Some(value)) => write!(f, "{}: hashed to {}", self.keyword, value),
+            (Action::ShiftDate(days), Some(value)) => {
+                write!(f, "{}: shifted by {} days to {}", self.keyword, days, value)
+            }
+        }
+    }
+}
+
+/// De-identifies TEXT keywords following a list of rules, the first rule
+/// matching a keyword deciding its action.
+#[derive(Clone, Debug, Default)]
+pub struct Anonymizer {
+    rules: Vec<Rule>,
+    salt: Vec<u8>,
+}
+
+impl Anonymizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn rule<P>(mut self, pattern: P, action: Action) -> Self
+    where
+        P: Into<String>,
+    {
+        self.rules.push(Rule {
+            pattern: pattern.into(),
+            action,
+        });
+        self
+    }
+
+    /// Secret mixed into hashed values, so they cannot be reversed by
+    /// hashing likely values.
+    pub fn salt<S>(mut self, salt: S) -> Self
+    where
+        S: Into<Vec<u8>>,
+    {
+        self.salt = salt.into();
+        self
+    }
+
+    /// Apply the rules to TEXT, returning the changes made.
+    ///
+    /// Values a date shift cannot parse fail with
+    /// [`FcsError::InvalidKeyword`] rather than being left in place. TEXT is
+    /// only changed when every rule applies.
+    pub fn anonymize(&self, original: &mut Text) -> Result<Vec<Change>> {
+        let mut text = original.clone();
+        let mut changes = vec![];
+        let keywords: Vec<String> = text.pairs.keys().cloned().collect();
+        for keyword in keywords {
+            let action = match self.rules.iter().find(|rule| rule.matches(&keyword)) {
+                Some(rule) => rule.action,
+                None => continue,
+            };
+            let value = &text.pairs[&keyword];
+
+            let value = match action {
+                Action::Drop => {
+                    text.remove(&keyword)?;
+                    None
+                }
+                Action::Hash => {
+                    let mut hasher = Sha256::new();
+                    hasher.update(&self.salt);
+                    hasher.update(value.as_bytes());
+                    let hash: String = hasher
+                        .finalize()
+                        .iter()
+                        .map(|byte| format!("{:02x}", byte))
+                        .collect();
+                    text.set(&keyword, hash.clone())?;
+                    Some(hash)
+                }
+                Action::ShiftDate(days) => {
+                    let invalid = || FcsError::InvalidKeyword {
+                        keyword: keyword.clone(),
+                        value: value.clone(),
+                    };
+                    let (date, format, rest) = Date::parse_prefix(value).ok_or_else(invalid)?;
+                    let date = date.add_days(days).ok_or_else(invalid)?;
+                    let shifted = format!("{}{}", date.format(format), rest);
+                    text.set(&keyword, shifted.clone())?;
+                    Some(shifted)
+                }
+            };
+
+            changes.push(Change {
+                keyword,
+                action,
+                value,
+            });
+        }
+        *original = text;
+
+        Ok(changes)
+    }
+
+    /// Anonymize the first data set read from reader and write it to
+    /// writer, copying DATA verbatim.
+    pub fn anonymize_file<R, W>(&self, reader: &mut R, writer: &mut W) -> Result<Vec<Change>>
+    where
+        R: FcsRead,
+        W: Write + ?Sized,
+    {
+        let mut metadata = reader.read_metadata()?;
+        let changes = self.anonymize(&mut metadata.text)?;
+        reader.rewrite(
+            &metadata.header,
+            &metadata.text,
+            metadata.analysis.as_ref(),
+            writer,
+        )?;
+
+        Ok(changes)
+    }
+}
+
+/// Match text against a pattern where `*` stands for any bytes.
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+        Some((byte, rest)) => text.first() == Some(byte) && glob(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Cursor, path::PathBuf};
+
+    use crate::{
+        error::{FcsError, Result},
+        text::Text,
+        traits::FcsRead,
+    };
+
+    use super::{Action, Anonymizer, Change};
+
+    #[test]
+    fn it_scrubs_clinical_keywords() -> Result<()> {
+        let mut file =
+            File::open(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs"))?;
+        let anonymizer = Anonymizer::new()
+            .salt("study")
+            .rule("CD * Date", Action::ShiftDate(-30))
+            .rule("CD *", Action::Drop)
+            .rule("$DATE", Action::ShiftDate(-30))
+            .rule("$OP", Action::Hash);
+
+        let mut bytes = vec![];
+        let changes = anonymizer.anonymize_file(&mut file, &mut bytes)?;
+        let fcs = Cursor::new(bytes).read_fcs()?;
+
+        assert_eq!(fcs.text.get("CD Age"), None);
+        assert_eq!(fcs.text.get("$DATE").unwrap(), "17-JUN-2007");
+        assert_eq!(
+            fcs.text.get("CD First Viral Load Date").unwrap(),
+            "10/10/1999"
+        );
+        assert_eq!(fcs.text.get("$OP").unwrap().len(), 64);
+        assert!(changes.contains(&Change {
+            keyword: "CD Age".into(),
+            action: Action::Drop,
+            value: None,
+        }));
+        assert!(changes
+            .iter()
+            .all(|change| !change.to_string().contains("Administrator")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_leaves_text_untouched_when_a_rule_fails() {
+        let mut text = Text::new(b"\\$OP\\Administrator\\$P1N\\FSC-A\\").unwrap();
+
+        assert!(Anonymizer::new()
+            .rule("*", Action::Drop)
+            .anonymize(&mut text)
+            .is_err());
+        assert_eq!(text.get("$OP").unwrap(), "Administrator");
+
+        let mut text = Text::new(b"\\$DATE\\17-JUL-2007\\").unwrap();
+        assert!(matches!(
+            Anonymizer::new()
+                .rule("$DATE", Action::ShiftDate(i64::MAX))
+                .anonymize(&mut text),
+            Err(FcsError::InvalidKeyword { .. })
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+use std::{future::Future, io::SeekFrom};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::{
+    error::{FcsError, Result},
+    fcs::{Fcs, FcsMetadata},
+    header::{Header, HEADER_LENGTH},
+    traits::{decode_data, segment_range, MetadataReader, Step},
+};
+
+/// Async counterpart of [`ByteRead`](crate::traits::ByteRead).
+pub trait AsyncByteRead: AsyncRead + AsyncSeek + Unpin + Send {
+    /// Read in bytes from start to stop inclusive, both relative to offset.
+    fn read_bytes(
+        &mut self,
+        offset: u64,
+        start: u64,
+        stop: u64,
+    ) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        async move {
+            let length = self.seek(SeekFrom::End(0)).await?;
+            let (first_byte, bytes_to_read) = segment_range(offset, start, stop, length)?;
+            self.seek(SeekFrom::Start(first_byte)).await?;
+
+            let mut result = vec![];
+            (&mut *self)
+                .take(bytes_to_read)
+                .read_to_end(&mut result)
+                .await?;
+
+            if (result.len() as u64) < bytes_to_read {
+                return Err(FcsError::TruncatedSegment {
+                    offset: first_byte,
+                    expected: bytes_to_read,
+                    found: result.len() as u64,
+                });
+            }
+
+            Ok(result)
+        }
+    }
+}
+
+/// Async counterpart of [`FcsRead`](crate::traits::FcsRead).
+pub trait AsyncFcsRead: AsyncByteRead + Sized {
+    /// Read the first data set in the file.
+    fn read_fcs(&mut self) -> impl Future<Output = Result<Fcs>> + Send {
+        self.read_fcs_at(0)
+    }
+
+    /// Read the data set whose HEADER starts at offset.
+    fn read_fcs_at(&mut self, offset: u64) -> impl Future<Output = Result<Fcs>> + Send {
+        async move {
+            let FcsMetadata {
+                header,
+                text,
+                analysis,
+            } = self.read_metadata_at(offset).await?;
+
+            let bytes = match (header.data_start, header.data_end) {
+                (0, 0) => vec![],
+                (start, end) => self.read_bytes(offset, start, end).await?,
+            };
+            let data = decode_data(&text, &bytes)?;
+
+            Ok(Fcs {
+                header,
+                text,
+                data,
+                analysis,
+                compensation: None,
+            })
+        }
+    }
+
+    /// Read HEADER, TEXT and ANALYSIS of the first data set, leaving DATA
+    /// unread.
+    fn read_metadata(&mut self) -> impl Future<Output = Result<FcsMetadata>> + Send {
+        self.read_metadata_at(0)
+    }
+
+    /// Read HEADER, TEXT and ANALYSIS of the data set whose HEADER starts
+    /// at offset, leaving DATA unread.
+    fn read_metadata_at(
+        &mut self,
+        offset: u64,
+    ) -> impl Future<Output = Result<FcsMetadata>> + Send {
+        async move {
+            let mut bytes = Vec::with_capacity(HEADER_LENGTH as usize);
+            self.seek(SeekFrom::Start(offset)).await?;
+            (&mut *self)
+                .take(HEADER_LENGTH)
+                .read_to_end(&mut bytes)
+                .await?;
+
+            let (mut segments, mut step) =
+                MetadataReader::new(Header::parse(&bytes, offset)?, true);
+            loop {
+                step = match step {
+                    Step::Read { start, stop } => {
+                        segments.resume(&self.read_bytes(offset, start, stop).await?)?
+                    }
+                    Step::Done(metadata) => return Ok(metadata),
+                };
+            }
+        }
+    }
+}
+
+impl<T> AsyncByteRead for T where T: AsyncRead + AsyncSeek + Unpin + Send {}
+
+impl<T> AsyncFcsRead for T where T: AsyncRead + AsyncSeek + Unpin + Send {}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Cursor, path::PathBuf};
+
+    use tokio::runtime::Builder;
+
+    use crate::{
+        data::Data,
+        error::{FcsError, Result},
+        fcs::Fcs,
+    };
+
+    use super::{AsyncByteRead, AsyncFcsRead};
+
+    #[test]
+    fn it_reads_from_an_async_buffer() -> Result<()> {
+        let bytes =
+            fs::read(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs"))?;
+        let expected = Fcs::from_bytes(&bytes)?;
+
+        let runtime = Builder::new_current_thread().build()?;
+        let fcs = runtime.block_on(async { Cursor::new(bytes).read_fcs().await })?;
+
+        assert_eq!(fcs.text.pairs, expected.text.pairs);
+        assert_eq!(fcs.header.data_end, expected.header.data_end);
+        match (fcs.data, expected.data) {
+            (Data::Float(actual), Data::Float(expected)) => assert_eq!(actual, expected),
+            other => panic!("unexpected data: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_metadata_and_rejects_segments_past_the_end() -> Result<()> {
+        let bytes =
+            fs::read(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs"))?;
+        let expected = Fcs::from_bytes(&bytes)?;
+
+        let runtime = Builder::new_current_thread().build()?;
+        let mut cursor = Cursor::new(bytes);
+        let metadata = runtime.block_on(cursor.read_metadata())?;
+        assert_eq!(metadata.text.pairs, expected.text.pairs);
+
+        assert!(matches!(
+            runtime.block_on(cursor.read_bytes(0, 10, u64::MAX)),
+            Err(FcsError::InvalidSegment { .. })
+        ));
+        assert!(matches!(
+            runtime.block_on(cursor.read_bytes(0, 10, i64::MAX as u64)),
+            Err(FcsError::TruncatedSegment { .. })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    data::{Byteord, Data, DataType},
+    error::{FcsError, Result},
+    fcs::Fcs,
+    header::Header,
+    keywords::{ParameterKeyword, RequiredKeyword},
+    text::{editable, Text},
+};
+
+/// Description of one parameter (channel) of a file being built.
+#[derive(Clone, Debug)]
+pub struct ParameterDefinition {
+    /// $PnN
+    pub short_name: String,
+    /// $PnS
+    pub label: Option<String>,
+    /// $PnR
+    pub range: u64,
+    /// $PnB, defaulting to the width of the file's $DATATYPE
+    pub bits: Option<u32>,
+    /// $PnE, defaulting to linear "0,0"
+    pub amplification: Option<String>,
+}
+
+impl ParameterDefinition {
+    pub fn new<S>(short_name: S, range: u64) -> Self
+    where
+        S: Into<String>,
+    {
+        ParameterDefinition {
+            short_name: short_name.into(),
+            label: None,
+            range,
+            bits: None,
+            amplification: None,
+        }
+    }
+
+    pub fn label<S>(mut self, label: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn bits(mut self, bits: u32) -> Self {
+        self.bits = Some(bits);
+        self
+    }
+
+    pub fn amplification<S>(mut self, amplification: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.amplification = Some(amplification.into());
+        self
+    }
+}
+
+/// Builds an [`Fcs`] from parameter definitions, events and metadata,
+/// deriving every keyword that describes the DATA segment.
+pub struct FcsBuilder {
+    data_type: DataType,
+    byteord: Byteord,
+    parameters: Vec<ParameterDefinition>,
+    events: Vec<f64>,
+    rows: usize,
+    ragged_row: Option<(usize, usize)>,
+    keywords: BTreeMap<String, String>,
+}
+
+impl FcsBuilder {
+    pub fn new(data_type: DataType) -> Self {
+        FcsBuilder {
+            data_type,
+            byteord: Byteord::LittleEndian("1,2,3,4".into()),
+            parameters: vec![],
+            events: vec![],
+            rows: 0,
+            ragged_row: None,
+            keywords: BTreeMap::new(),
+        }
+    }
+
+    pub fn byteord(mut self, byteord: Byteord) -> Self {
+        self.byteord = byteord;
+        self
+    }
+
+    pub fn parameter(mut self, parameter: ParameterDefinition) -> Self {
+        self.parameters.push(parameter);
+        self
+    }
+
+    /// Append events, each holding one value per parameter in order.
+    pub fn events<I, E>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = E>,
+        E: AsRef<[f64]>,
+    {
+        for event in events {
+            let event = event.as_ref();
+            if self.ragged_row.is_none() && event.len() != self.parameters.len() {
+                self.ragged_row = Some((self.rows, event.len()));
+            }
+            self.events.extend_from_slice(event);
+            self.rows += 1;
+        }
+        self
+    }
+
+    /// Set a metadata keyword, such as $FIL or $CYT. [`FcsBuilder::build`]
+    /// rejects `$` keywords the standard does not define or that describe
+    /// DATA, as [`Text::set`] does.
+    pub fn keyword<K, V>(mut self, keyword: K, value: V) -> Self
+    where
+        K: ToString,
+        V: Into<String>,
+    {
+        self.keywords.insert(keyword.to_string(), value.into());
+        self
+    }
+
+    /// Validate the definitions against the events and assemble the file.
+    pub fn build(self) -> Result<Fcs> {
+        if self.parameters.is_empty() {
+            return Err(FcsError::MissingKeyword(ParameterKeyword::ShortName.of(1)));
+        }
+        if let Some((row, values)) = self.ragged_row {
+            return Err(FcsError::InvalidEvent {
+                event: row,
+                expected: self.parameters.len(),
+                found: values,
+            });
+        }
+
+        let mut pairs = BTreeMap::new();
+        let byteord = match &self.byteord {
+            Byteord::BigEndian(_) => "4,3,2,1",
+            Byteord::LittleEndian(_) => "1,2,3,4",
+        };
+        let data_type = match self.data_type {
+            DataType::Ascii => "A",
+            DataType::Int => "I",
+            DataType::Float => "F",
+            DataType::Double => "D",
+        };
+        pairs.insert(RequiredKeyword::Byteord.to_string(), byteord.to_string());
+        pairs.insert(RequiredKeyword::DataType.to_string(), data_type.to_string());
+        pairs.insert(RequiredKeyword::Mode.to_string(), "L".to_string());
+        pairs.insert(
+            RequiredKeyword::Par.to_string(),
+            self.parameters.len().to_string(),
+        );
+        pairs.insert(RequiredKeyword::Tot.to_string(), self.rows.to_string());
+
+        let mut short_names = BTreeSet::new();
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            let index = i as u32 + 1;
+            let bits = parameter.bits.unwrap_or(match self.data_type {
+                // Enough digits for the largest channel.
+                DataType::Ascii => parameter.range.saturating_sub(1).to_string().len() as u32,
+                DataType::Double => 64,
+                _ => 32,
+            });
+            validate(self.data_type, index, parameter, bits)?;
+
+            if !short_names.insert(parameter.short_name.as_str()) {
+                return Err(FcsError::InvalidKeyword {
+                    keyword: ParameterKeyword::ShortName.of(index),
+                    value: parameter.short_name.clone(),
+                });
+            }
+
+            pairs.insert(ParameterKeyword::Bits.of(index), bits.to_string());
+            pairs.insert(
+                ParameterKeyword::Amplification.of(index),
+                parameter
+                    .amplification
+                    .clone()
+                    .unwrap_or_else(|| "0,0".into()),
+            );
+            pairs.insert(
+                ParameterKeyword::ShortName.of(index),
+                parameter.short_name.clone(),
+            );
+            pairs.insert(
+                ParameterKeyword::Range.of(index),
+                parameter.range.to_string(),
+            );
+            if let Some(label) = &parameter.label {
+                pairs.insert(ParameterKeyword::LongName.of(index), label.clone());
+            }
+        }
+
+        for (keyword, value) in self.keywords {
+            if pairs.contains_key(&keyword) || keyword.is_empty() || value.is_empty() {
+                return Err(FcsError::InvalidKeyword { keyword, value });
+            }
+            editable(&keyword)?;
+            pairs.insert(keyword, value);
+        }
+
+        if matches!(self.data_type, DataType::Ascii | DataType::Int) {
+            // Both are written as integers, which must fit below $PnR.
+            for (i, value) in self.events.iter().enumerate() {
+                let range = self.parameters[i % self.parameters.len()].range;
+                if *value < 0.0 || value.fract() != 0.0 || *value as u64 >= range.max(1) {
+                    return Err(FcsError::InvalidData {
+                        offset: i as u64,
+                        value: value.to_string(),
+                    });
+                }
+            }
+        }
+
+        let data = match self.data_type {
+            DataType::Ascii => Data::Ascii(self.events),
+            DataType::Int => Data::Int(self.events.iter().map(|v| *v as u64).collect()),
+            DataType::Float => Data::Float(self.events.iter().map(|v| *v as f32).collect()),
+            DataType::Double => Data::Double(self.events),
+        };
+
+        Ok(Fcs {
+            header: Header {
+                offset: 0,
+                version: 3.1,
+                text_start: 0,
+                text_end: 0,
+                data_start: 0,
+                data_end: 0,
+                analysis_start: None,
+                analysis_end: None,
+            },
+            text: Text {
+                pairs,
+                supplemental: BTreeSet::new(),
+            },
+            data,
+            analysis: None,
+            compensation: None,
+        })
+    }
+}
+
+/// Check that $PnB is one the data type can be written with.
+fn validate(
+    data_type: DataType,
+    index: u32,
+    parameter: &ParameterDefinition,
+    bits: u32,
+) -> Result<()> {
+    let valid = match data_type {
+        DataType::Ascii => bits > 0,
+        DataType::Int => {
+            bits.is_multiple_of(8)
+                && (8..=64).contains(&bits)
+                && (bits == 64 || parameter.range <= 1 << bits)
+        }
+        DataType::Float => bits == 32,
+        DataType::Double => bits == 64,
+    };
+    if !valid {
+        return Err(FcsError::InvalidKeyword {
+            keyword: ParameterKeyword::Bits.of(index),
+            value: bits.to_string(),
+        });
+    }
+
+    if parameter.short_name.is_empty() || parameter.short_name.contains(',') {
+        return Err(FcsError::InvalidKeyword {
+            keyword: ParameterKeyword::ShortName.of(index),
+            value: parameter.short_name.clone(),
+        });
+    }
+
+    if parameter.range == 0 {
+        return Err(FcsError::InvalidKeyword {
+            keyword: ParameterKeyword::Range.of(index),
+            value: parameter.range.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        data::{Data, DataType},
+        error::FcsError,
+        traits::{FcsRead, FcsWrite},
+    };
+
+    use super::{FcsBuilder, ParameterDefinition};
+
+    #[test]
+    fn it_builds_a_file_that_reads_back() {
+        let fcs = FcsBuilder::new(DataType::Int)
+            .parameter(ParameterDefinition::new("FSC-H", 1024).bits(16))
+            .parameter(
+                ParameterDefinition::new("FL1-H", 1024)
+                    .bits(16)
+                    .label("CD3"),
+            )
+            .events([[10.0, 20.0], [30.0, 40.0], [50.0, 60.0]])
+            .keyword("$FIL", "simulated.fcs")
+            .build()
+            .unwrap();
+
+        let mut bytes = vec![];
+        bytes.write_fcs(&fcs).unwrap();
+        let fcs = Cursor::new(bytes).read_fcs().unwrap();
+
+        assert_eq!(fcs.text.total_events().unwrap(), 3);
+        assert_eq!(fcs.text.get("$P2S").unwrap(), "CD3");
+        assert_eq!(fcs.text.get("$P1E").unwrap(), "0,0");
+        assert_eq!(fcs.text.get("$FIL").unwrap(), "simulated.fcs");
+        match fcs.data {
+            Data::Int(values) => assert_eq!(values, vec![10, 20, 30, 40, 50, 60]),
+            other => panic!("unexpected data: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_rejects_events_of_the_wrong_width() {
+        let result = FcsBuilder::new(DataType::Float)
+            .parameter(ParameterDefinition::new("FSC-A", 262144))
+            .parameter(ParameterDefinition::new("SSC-A", 262144))
+            .events(vec![vec![1.0, 2.0], vec![3.0]])
+            .build();
+
+        match result {
+            Err(FcsError::InvalidEvent {
+                event,
+                expected,
+                found,
+            }) => assert_eq!((event, expected, found), (1, 2, 1)),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn it_rejects_keywords_it_derives() {
+        let result = FcsBuilder::new(DataType::Float)
+            .parameter(ParameterDefinition::new("FSC-A", 262144))
+            .keyword("$TOT", "5")
+            .build();
+
+        assert!(matches!(result, Err(FcsError::InvalidKeyword { .. })));
+
+        for keyword in ["$FOO", "$NEXTDATA", "$P1DATATYPE"] {
+            let result = FcsBuilder::new(DataType::Float)
+                .parameter(ParameterDefinition::new("FSC-A", 262144))
+                .keyword(keyword, "1")
+                .build();
+
+            assert!(matches!(result, Err(FcsError::ReservedKeyword(_))));
+        }
+    }
+
+    #[test]
+    fn it_rejects_ascii_values_it_cannot_write() {
+        for value in [12.7, -5.0, 1500.0] {
+            let result = FcsBuilder::new(DataType::Ascii)
+                .parameter(ParameterDefinition::new("FSC-A", 1024).bits(4))
+                .events([[value]])
+                .build();
+
+            assert!(matches!(result, Err(FcsError::InvalidData { .. })));
+        }
+    }
+}
@@ -0,0 +1,522 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    error::{FcsError, Result},
+    events::Events,
+    fcs::Fcs,
+    keywords::OptionalKeyword,
+    text::{Parameter, Text},
+};
+
+/// Keywords holding a spillover matrix in the $SPILLOVER format, in order
+/// of preference.
+const SPILLOVER_KEYWORDS: &[&str] = &["$SPILLOVER", "SPILL", "SPILLOVER"];
+
+/// FCS 2.0/3.0 compensation matrix, without parameter names.
+const COMP: &str = "$COMP";
+
+/// Keyword [`Fcs::compensate`] records the matrix it applied in, in the
+/// $SPILLOVER format, so that compensation survives writing the file.
+pub const COMPENSATED: &str = "COMPENSATED";
+
+/// Quantile of the unstained control above which events of a single-stain
+/// control count as positive.
+const POSITIVE_QUANTILE: f64 = 0.995;
+
+/// Fewest positive events a single-stain control must hold.
+const MIN_POSITIVE_EVENTS: usize = 10;
+
+/// A data set stained with a single fluorochrome, with the parameter that
+/// fluorochrome is primarily detected in.
+#[derive(Clone, Copy)]
+pub struct Control<'a> {
+    pub fcs: &'a Fcs,
+    /// $PnN or $PnS of the primary parameter.
+    pub parameter: &'a str,
+}
+
+/// Square matrix of the fraction of each fluorochrome's signal detected by
+/// each parameter, row i holding the spillover of the fluorochrome measured
+/// by parameter i into every parameter.
+///
+/// Parameters are named rather than numbered, so that a matrix applies to
+/// any data set holding them, in whatever order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpilloverMatrix {
+    /// $PnN of the parameter of each row and column.
+    pub names: Vec<String>,
+    /// Spillover values, row after row.
+    pub values: Vec<f64>,
+}
+
+impl SpilloverMatrix {
+    /// Matrix over the parameters with the given $PnN, validating its
+    /// dimensions.
+    pub fn new(names: Vec<String>, values: Vec<f64>) -> Result<Self> {
+        let n = names.len();
+        if n == 0 || values.len() != n * n {
+            return Err(FcsError::InvalidKeyword {
+                keyword: OptionalKeyword::Spillover.to_string(),
+                value: format!("{} parameters, {} values", n, values.len()),
+            });
+        }
+
+        let mut sorted = names.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        if sorted.len() != n {
+            return Err(FcsError::InvalidKeyword {
+                keyword: OptionalKeyword::Spillover.to_string(),
+                value: names.join(","),
+            });
+        }
+
+        Ok(SpilloverMatrix { names, values })
+    }
+
+    /// Read the matrix from $SPILLOVER, the SPILL keyword BD instruments
+    /// write, $COMP or the FCS 2.0 $DFCiTOj keywords, whichever is found
+    /// first.
+    pub fn from_text(text: &Text) -> Result<Option<Self>> {
+        let parameters = text.parameters()?;
+
+        for keyword in SPILLOVER_KEYWORDS {
+            if let Some(value) = text.get(keyword) {
+                return parse_spillover(keyword, value, &parameters).map(Some);
+            }
+        }
+        if let Some(value) = text.get(COMP) {
+            return parse_comp(value, &parameters).map(Some);
+        }
+
+        parse_dfc(text, &parameters)
+    }
+
+    /// Estimate the matrix from single-stain controls, one per row, and an
+    /// unstained control, naming parameters by their $PnN in the latter.
+    ///
+    /// The positive population of a control is the events brighter in its
+    /// primary parameter than 99.5% of the unstained events. Each spillover
+    /// is the median slope from the unstained median to those events, a
+    /// regression that, unlike least squares, holds against outliers.
+    pub fn from_controls(unstained: &Fcs, controls: &[Control<'_>]) -> Result<Self> {
+        let unstained = unstained.events()?;
+        let columns = controls
+            .iter()
+            .map(|control| column_of(&unstained, control.parameter))
+            .collect::<Result<Vec<_>>>()?;
+        let names: Vec<String> = columns
+            .iter()
+            .map(|&c| unstained.parameters()[c].short_name.clone())
+            .collect();
+
+        let mut background = Vec::with_capacity(columns.len());
+        let mut thresholds = Vec::with_capacity(columns.len());
+        for &column in &columns {
+            let mut values = unstained.column(column).unwrap_or_default();
+            values.sort_unstable_by(f64::total_cmp);
+            let not_enough = || FcsError::NotEnoughEvents("unstained".to_string());
+            background.push(quantile(&values, 0.5).ok_or_else(not_enough)?);
+            thresholds.push(quantile(&values, POSITIVE_QUANTILE).ok_or_else(not_enough)?);
+        }
+
+        let n = columns.len();
+        let mut values = vec![0.0; n * n];
+        for (row, control) in controls.iter().enumerate() {
+            let events = control.fcs.events()?;
+            let observed = names
+                .iter()
+                .map(|name| column_of(&events, name).map(|c| events.column(c).unwrap_or_default()))
+                .collect::<Result<Vec<_>>>()?;
+
+            let positive: Vec<usize> = (0..events.len())
+                .filter(|&event| observed[row][event] > thresholds[row])
+                .collect();
+            if positive.len() < MIN_POSITIVE_EVENTS {
+                return Err(FcsError::NotEnoughEvents(control.parameter.to_string()));
+            }
+
+            for column in 0..n {
+                let mut slopes: Vec<f64> = positive
+                    .iter()
+                    .map(|&event| {
+                        (observed[column][event] - background[column])
+                            / (observed[row][event] - background[row])
+                    })
+                    .collect();
+                slopes.sort_unstable_by(f64::total_cmp);
+                values[row * n + column] = if column == row {
+                    1.0
+                } else {
+                    quantile(&slopes, 0.5).unwrap_or_default()
+                };
+            }
+        }
+
+        SpilloverMatrix::new(names, values)
+    }
+
+    /// Value of $SPILLOVER for the matrix.
+    pub fn to_keyword(&self) -> String {
+        let mut fields = vec![self.dimension().to_string()];
+        fields.extend(self.names.iter().cloned());
+        fields.extend(self.values.iter().map(f64::to_string));
+
+        fields.join(",")
+    }
+
+    /// Number of rows, and of columns.
+    pub fn dimension(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Column of each row and column of the matrix among the parameters,
+    /// starting from 0.
+    pub fn columns(&self, parameters: &[Parameter]) -> Result<Vec<usize>> {
+        self.names
+            .iter()
+            .map(|name| {
+                position(parameters, name).ok_or_else(|| FcsError::UnknownParameter(name.clone()))
+            })
+            .collect()
+    }
+
+    /// Spillover of the fluorochrome in row into the parameter in column,
+    /// both positions in the matrix.
+    pub fn get(&self, row: usize, column: usize) -> Option<f64> {
+        let n = self.dimension();
+        if row >= n || column >= n {
+            return None;
+        }
+
+        Some(self.values[row * n + column])
+    }
+
+    /// Inverse of the matrix, row after row, which turns observed values
+    /// into compensated ones.
+    pub fn inverse(&self) -> Result<Vec<f64>> {
+        invert(&self.values, self.dimension()).ok_or(FcsError::SingularMatrix)
+    }
+
+    /// Replace the observed values of an event, one per parameter, with
+    /// compensated values, given the matrix inverse and its columns.
+    pub(crate) fn apply(&self, inverse: &[f64], columns: &[usize], event: &mut [f64]) {
+        let n = self.dimension();
+        let observed: Vec<f64> = columns.iter().map(|&c| event[c]).collect();
+        for (j, &column) in columns.iter().enumerate() {
+            event[column] = (0..n).map(|i| observed[i] * inverse[i * n + j]).sum();
+        }
+    }
+}
+
+/// Invert an n by n matrix by Gauss-Jordan elimination with partial
+/// pivoting, or None when it is singular.
+fn invert(matrix: &[f64], n: usize) -> Option<Vec<f64>> {
+    let mut a = matrix.to_vec();
+    let mut inverse = vec![0.0; n * n];
+    for i in 0..n {
+        inverse[i * n + i] = 1.0;
+    }
+
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&r, &s| a[r * n + column].abs().total_cmp(&a[s * n + column].abs()))?;
+        if a[pivot * n + column].abs() < 1e-12 {
+            return None;
+        }
+        for k in 0..n {
+            a.swap(pivot * n + k, column * n + k);
+            inverse.swap(pivot * n + k, column * n + k);
+        }
+
+        let scale = a[column * n + column];
+        for k in 0..n {
+            a[column * n + k] /= scale;
+            inverse[column * n + k] /= scale;
+        }
+        for row in (0..n).filter(|&row| row != column) {
+            let factor = a[row * n + column];
+            for k in 0..n {
+                a[row * n + k] -= factor * a[column * n + k];
+                inverse[row * n + k] -= factor * inverse[column * n + k];
+            }
+        }
+    }
+
+    Some(inverse)
+}
+
+/// Parse `n,name1,...,namen,f11,f12,...,fnn`, names being $PnN or $PnS.
+fn parse_spillover(
+    keyword: &str,
+    value: &str,
+    parameters: &[Parameter],
+) -> Result<SpilloverMatrix> {
+    let invalid = || FcsError::InvalidKeyword {
+        keyword: keyword.to_string(),
+        value: value.to_string(),
+    };
+
+    let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+    let n: usize = fields
+        .first()
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(invalid)?;
+    if n > parameters.len() || fields.len() != 1 + n + n * n {
+        return Err(invalid());
+    }
+
+    let names = fields[1..=n]
+        .iter()
+        .map(|name| {
+            position(parameters, name)
+                .map(|i| parameters[i].short_name.clone())
+                .ok_or_else(|| FcsError::InvalidKeyword {
+                    keyword: keyword.to_string(),
+                    value: name.to_string(),
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let values = parse_values(&fields[1 + n..]).ok_or_else(invalid)?;
+    SpilloverMatrix::new(names, values)
+}
+
+/// Parse `n,f11,f12,...,fnn` over the first n parameters.
+fn parse_comp(value: &str, parameters: &[Parameter]) -> Result<SpilloverMatrix> {
+    let invalid = || FcsError::InvalidKeyword {
+        keyword: COMP.to_string(),
+        value: value.to_string(),
+    };
+
+    let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+    let n: usize = fields
+        .first()
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(invalid)?;
+    if n > parameters.len() || fields.len() != 1 + n * n {
+        return Err(invalid());
+    }
+
+    let values = parse_values(&fields[1..]).ok_or_else(invalid)?;
+    let names = parameters[..n]
+        .iter()
+        .map(|p| p.short_name.clone())
+        .collect();
+    SpilloverMatrix::new(names, values)
+}
+
+/// Assemble the matrix from $DFCiTOj, the percentage of the signal of
+/// parameter i detected in parameter j, over every parameter they name.
+fn parse_dfc(text: &Text, parameters: &[Parameter]) -> Result<Option<SpilloverMatrix>> {
+    let mut entries = BTreeMap::new();
+    for (keyword, value) in &text.pairs {
+        let indices = keyword
+            .to_ascii_uppercase()
+            .strip_prefix("$DFC")
+            .and_then(|rest| {
+                let (i, j) = rest.split_once("TO")?;
+                Some((i.parse::<usize>().ok()?, j.parse::<usize>().ok()?))
+            });
+        let (i, j) = match indices {
+            Some(indices) => indices,
+            None => continue,
+        };
+
+        let invalid = || FcsError::InvalidKeyword {
+            keyword: keyword.clone(),
+            value: value.clone(),
+        };
+        if i == 0 || j == 0 || i > parameters.len() || j > parameters.len() {
+            return Err(invalid());
+        }
+        let percent: f64 = value.trim().parse().map_err(|_| invalid())?;
+        entries.insert((i - 1, j - 1), percent / 100.0);
+    }
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    let mut columns: Vec<usize> = entries.keys().flat_map(|&(i, j)| [i, j]).collect();
+    columns.sort_unstable();
+    columns.dedup();
+
+    let n = columns.len();
+    let mut values = vec![0.0; n * n];
+    for (row, i) in columns.iter().enumerate() {
+        for (column, j) in columns.iter().enumerate() {
+            values[row * n + column] = match entries.get(&(*i, *j)) {
+                Some(value) => *value,
+                None if i == j => 1.0,
+                None => 0.0,
+            };
+        }
+    }
+
+    let names = columns
+        .iter()
+        .map(|&c| parameters[c].short_name.clone())
+        .collect();
+    SpilloverMatrix::new(names, values).map(Some)
+}
+
+/// Column of the parameter named by $PnN or $PnS.
+fn column_of(events: &Events<'_>, name: &str) -> Result<usize> {
+    events
+        .index_of(name)
+        .ok_or_else(|| FcsError::UnknownParameter(name.to_string()))
+}
+
+/// Quantile q of values sorted in ascending order.
+fn quantile(sorted: &[f64], q: f64) -> Option<f64> {
+    let last = sorted.len().checked_sub(1)?;
+    sorted.get((last as f64 * q).round() as usize).copied()
+}
+
+/// Position of the parameter whose $PnN, or failing that $PnS, is name.
+fn position(parameters: &[Parameter], name: &str) -> Option<usize> {
+    parameters
+        .iter()
+        .position(|p| p.short_name == name)
+        .or_else(|| {
+            parameters
+                .iter()
+                .position(|p| p.name.as_deref() == Some(name))
+        })
+}
+
+fn parse_values(fields: &[&str]) -> Option<Vec<f64>> {
+    fields.iter().map(|field| field.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, path::PathBuf};
+
+    use crate::{
+        builder::{FcsBuilder, ParameterDefinition},
+        data::DataType,
+        error::Result,
+        fcs::Fcs,
+        text::Text,
+        traits::FcsRead,
+    };
+
+    use super::{Control, SpilloverMatrix};
+
+    /// TEXT describing 16-bit parameters with the given $PnN, followed by
+    /// other keywords.
+    fn text(names: &[&str], keywords: &str) -> Text {
+        let mut text = format!("\\$PAR\\{}\\", names.len());
+        for (i, name) in names.iter().enumerate() {
+            text += &format!("$P{0}B\\16\\$P{0}N\\{1}\\$P{0}R\\1024\\", i + 1, name);
+        }
+        Text::new((text + keywords).as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn it_maps_the_spill_keyword_to_parameters() -> Result<()> {
+        let mut file =
+            File::open(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs"))?;
+        let metadata = file.read_metadata()?;
+
+        let matrix = SpilloverMatrix::from_text(&metadata.text)?.unwrap();
+        assert_eq!(matrix.dimension(), 13);
+        assert_eq!(
+            matrix.columns(&metadata.text.parameters()?)?,
+            (3..16).collect::<Vec<_>>()
+        );
+        assert_eq!(matrix.get(0, 0), Some(1.0));
+        assert_eq!(matrix.get(0, 3), Some(0.00008841570561316703));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_fcs2_compensation_keywords() -> Result<()> {
+        let text = text(&["FSC", "FL1", "FL2"], "$DFC2TO3\\12.5\\$DFC3TO2\\2\\");
+
+        let matrix = SpilloverMatrix::from_text(&text)?.unwrap();
+        assert_eq!(matrix.names, vec!["FL1", "FL2"]);
+        assert_eq!(matrix.values, vec![1.0, 0.125, 0.02, 1.0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_rejects_a_matrix_of_the_wrong_size() {
+        let short = text(&["FL1", "FL2"], "$SPILLOVER\\2,FL1,FL2,1,0,0\\");
+        assert!(SpilloverMatrix::from_text(&short).is_err());
+
+        let overflowing = text(&["FL1", "FL2"], "$SPILLOVER\\4294967296,A\\");
+        assert!(SpilloverMatrix::from_text(&overflowing).is_err());
+    }
+
+    /// Unstained-like events around (500, 10, 20), with stained events
+    /// spilling from the fluorochrome in `stained` into the other parameter,
+    /// and a few outliers.
+    fn control(stained: Option<usize>, spillover: f64) -> Result<Fcs> {
+        let mut seed = 12345u64;
+        let mut noise = move || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) as f64 / (1u64 << 31) as f64 * 10.0 - 5.0
+        };
+
+        let mut events = vec![];
+        for i in 0..1000 {
+            let mut event = [500.0 + noise(), 10.0 + noise(), 20.0 + noise()];
+            if let Some(stained) = stained.filter(|_| i % 5 == 0) {
+                let signal = 200.0 + i as f64 * 2.0;
+                event[stained] += signal;
+                event[3 - stained] += signal * spillover;
+            }
+            events.push(event);
+        }
+        if let Some(stained) = stained {
+            events.extend([[500.0, 0.0, 0.0]; 5].map(|mut event| {
+                event[stained] = 5000.0;
+                event[3 - stained] = 4000.0;
+                event
+            }));
+        }
+
+        FcsBuilder::new(DataType::Float)
+            .parameter(ParameterDefinition::new("FSC-A", 1024))
+            .parameter(ParameterDefinition::new("FL1-A", 4096).label("FITC"))
+            .parameter(ParameterDefinition::new("FL2-A", 4096).label("PE"))
+            .events(events)
+            .build()
+    }
+
+    #[test]
+    fn it_estimates_spillover_from_single_stain_controls() -> Result<()> {
+        let unstained = control(None, 0.0)?;
+        let fitc = control(Some(1), 0.15)?;
+        let mut pe = control(Some(2), 0.05)?;
+
+        let matrix = SpilloverMatrix::from_controls(
+            &unstained,
+            &[
+                Control {
+                    fcs: &fitc,
+                    parameter: "FITC",
+                },
+                Control {
+                    fcs: &pe,
+                    parameter: "FL2-A",
+                },
+            ],
+        )?;
+        assert_eq!(matrix.names, vec!["FL1-A", "FL2-A"]);
+        assert_eq!(matrix.get(0, 0), Some(1.0));
+        assert!((matrix.get(0, 1).unwrap() - 0.15).abs() < 0.01);
+        assert!((matrix.get(1, 0).unwrap() - 0.05).abs() < 0.01);
+
+        let keyword = matrix.to_keyword();
+        assert!(keyword.starts_with("2,FL1-A,FL2-A,1,"));
+        pe.text.set("$SPILLOVER", keyword)?;
+        assert_eq!(pe.text.spillover()?, Some(matrix));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+/// Outcome of checking the CRC that follows the last segment of a data set.
+#[derive(Debug, PartialEq)]
+pub enum Crc {
+    /// The stored CRC matches the one computed over the data set.
+    Valid(u16),
+    /// The writer stored "00000000", meaning no CRC was computed.
+    NotComputed,
+    /// Nothing follows the last segment, as in FCS 2.0 files.
+    Absent,
+}
+
+/// CRC-16/CCITT (polynomial 0x1021, initial value 0xFFFF) that FCS 3.x
+/// appends after the last segment, computed from the first HEADER byte.
+#[derive(Clone, Copy)]
+pub struct Crc16(u16);
+
+impl Default for Crc16 {
+    fn default() -> Self {
+        Crc16(0xFFFF)
+    }
+}
+
+impl Crc16 {
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= (byte as u16) << 8;
+            for _ in 0..8 {
+                self.0 = if self.0 & 0x8000 != 0 {
+                    (self.0 << 1) ^ 0x1021
+                } else {
+                    self.0 << 1
+                };
+            }
+        }
+    }
+
+    pub fn finish(&self) -> u16 {
+        self.0
+    }
+}
+
+/// CRC of bytes in one go.
+pub fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = Crc16::default();
+    crc.update(bytes);
+    crc.finish()
+}
+
+/// The 8 ASCII characters a writer appends after the last segment.
+pub fn footer(crc: u16) -> [u8; 8] {
+    let mut footer = [b'0'; 8];
+    footer.copy_from_slice(format!("{:08}", crc).as_bytes());
+    footer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc16, footer};
+
+    #[test]
+    fn it_computes_the_ccitt_crc() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn it_formats_the_footer() {
+        assert_eq!(&footer(0x29B1), b"00010673");
+    }
+}
@@ -1,100 +1,798 @@
-use std::{io, mem};
+use std::{mem, str::FromStr};
 
-use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
 
+use crate::{
+    error::{FcsError, Result},
+    histogram::Histogram,
+    keywords::ParameterKeyword,
+    text::Parameter,
+};
+
+#[derive(Clone, Debug)]
 pub enum Byteord {
     LittleEndian(String),
     BigEndian(String),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DataType {
+    Ascii,
     Int,
     Float,
     Double,
 }
 
+/// $MODE: how events are stored in the DATA segment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    List,
+    Correlated,
+    Uncorrelated,
+}
+
 #[derive(Debug)]
 pub enum Data {
-    Int(Vec<i32>),
+    /// ASCII values, either fixed-width or delimited.
+    Ascii(Vec<f64>),
+    /// Unsigned integers, masked to each parameter's $PnR.
+    Int(Vec<u64>),
     Float(Vec<f32>),
     Double(Vec<f64>),
+    /// Events whose parameters use different $PnDATATYPE (FCS 3.2).
+    Mixed(Vec<Value>),
+    /// Histogram counts, for $MODE=C and $MODE=U.
+    Histogram(Histogram),
 }
 
-impl<S> From<S> for Byteord
-where
-    S: AsRef<str>,
-{
-    fn from(byteord: S) -> Self {
-        let byteord = byteord.as_ref();
+/// A single value of a mixed-type event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Int(u64),
+    Float(f32),
+    Double(f64),
+}
+
+impl Value {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Value::Int(value) => value as f64,
+            Value::Float(value) => value as f64,
+            Value::Double(value) => value,
+        }
+    }
+
+    /// Nearest unsigned integer, saturating at the bounds of u64.
+    pub fn as_u64(&self) -> u64 {
+        match *self {
+            Value::Int(value) => value,
+            _ => self.as_f64().round() as u64,
+        }
+    }
+}
+
+impl FromStr for Byteord {
+    type Err = FcsError;
+
+    fn from_str(byteord: &str) -> Result<Self> {
         match byteord {
-            "4,3,2,1" | "2,1" => Byteord::BigEndian(byteord.to_string()),
-            "1,2,3,4" | "1,2" => Byteord::LittleEndian(byteord.to_string()),
-            _ => panic!("invalid byteorder"),
+            "4,3,2,1" | "2,1" => Ok(Byteord::BigEndian(byteord.to_string())),
+            "1,2,3,4" | "1,2" => Ok(Byteord::LittleEndian(byteord.to_string())),
+            _ => Err(FcsError::UnsupportedByteord(byteord.to_string())),
         }
     }
 }
 
-impl<S> From<S> for DataType
-where
-    S: AsRef<str>,
-{
-    fn from(data_type: S) -> Self {
-        let data_type = data_type.as_ref();
+impl FromStr for DataType {
+    type Err = FcsError;
+
+    fn from_str(data_type: &str) -> Result<Self> {
         match data_type {
-            "I" => DataType::Int,
-            "F" => DataType::Float,
-            "D" => DataType::Double,
-            _ => panic!("invalid format"),
+            "A" => Ok(DataType::Ascii),
+            "I" => Ok(DataType::Int),
+            "F" => Ok(DataType::Float),
+            "D" => Ok(DataType::Double),
+            _ => Err(FcsError::UnsupportedDataType(data_type.to_string())),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = FcsError;
+
+    fn from_str(mode: &str) -> Result<Self> {
+        match mode {
+            "L" => Ok(Mode::List),
+            "C" => Ok(Mode::Correlated),
+            "U" => Ok(Mode::Uncorrelated),
+            _ => Err(FcsError::InvalidKeyword {
+                keyword: "$MODE".into(),
+                value: mode.to_string(),
+            }),
         }
     }
 }
 
 impl Data {
-    pub fn new(bytes: &Vec<u8>, data_type: DataType, byteord: Byteord) -> io::Result<Self> {
+    /// Decode the DATA segment, whose bytes must be a whole number of values.
+    pub fn new(
+        bytes: &[u8],
+        data_type: DataType,
+        byteord: Byteord,
+        parameters: &[Parameter],
+    ) -> Result<Self> {
+        if parameters
+            .iter()
+            .any(|p| p.data_type.is_some_and(|t| t != data_type))
+        {
+            return decode_mixed(bytes, data_type, byteord, parameters).map(Data::Mixed);
+        }
+
         match data_type {
-            DataType::Int => {
-                let chunks = bytes.chunks(mem::size_of::<u32>());
-
-                let mut wrt: Vec<i32> = vec![];
-                for chunk in chunks {
-                    let mut rdr = std::io::Cursor::new(chunk);
-                    wrt.push(match byteord {
-                        Byteord::BigEndian(_) => rdr.read_i32::<BigEndian>()?,
-                        Byteord::LittleEndian(_) => rdr.read_i32::<LittleEndian>()?,
-                    });
+            DataType::Ascii => decode_ascii(bytes, parameters).map(Data::Ascii),
+            DataType::Int => decode_int(bytes, byteord, parameters).map(Data::Int),
+            DataType::Float => {
+                let values = decode(bytes, mem::size_of::<f32>(), |chunk| match byteord {
+                    Byteord::BigEndian(_) => BigEndian::read_f32(chunk),
+                    Byteord::LittleEndian(_) => LittleEndian::read_f32(chunk),
+                })?;
+
+                Ok(Data::Float(values))
+            }
+            DataType::Double => {
+                let values = decode(bytes, mem::size_of::<f64>(), |chunk| match byteord {
+                    Byteord::BigEndian(_) => BigEndian::read_f64(chunk),
+                    Byteord::LittleEndian(_) => LittleEndian::read_f64(chunk),
+                })?;
+
+                Ok(Data::Double(values))
+            }
+        }
+    }
+}
+
+impl Data {
+    /// Number of values, or of counts for histograms.
+    pub fn len(&self) -> usize {
+        match self {
+            Data::Ascii(values) => values.len(),
+            Data::Int(values) => values.len(),
+            Data::Float(values) => values.len(),
+            Data::Double(values) => values.len(),
+            Data::Mixed(values) => values.len(),
+            Data::Histogram(histogram) => histogram.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Value at index in list mode data, in event-major order.
+    pub fn get(&self, index: usize) -> Option<Value> {
+        match self {
+            Data::Ascii(values) => values.get(index).map(|v| Value::Double(*v)),
+            Data::Int(values) => values.get(index).map(|v| Value::Int(*v)),
+            Data::Float(values) => values.get(index).map(|v| Value::Float(*v)),
+            Data::Double(values) => values.get(index).map(|v| Value::Double(*v)),
+            Data::Mixed(values) => values.get(index).copied(),
+            Data::Histogram(_) => None,
+        }
+    }
+
+    /// Encode as the bytes of a DATA segment, converting every value to the
+    /// type its parameter declares.
+    pub fn to_bytes(
+        &self,
+        data_type: DataType,
+        byteord: &Byteord,
+        parameters: &[Parameter],
+    ) -> Result<Vec<u8>> {
+        if let Data::Histogram(histogram) = self {
+            return histogram.to_bytes(data_type, byteord, parameters);
+        }
+        if parameters.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let delimited = data_type == DataType::Ascii && parameters.iter().all(|p| p.bits.is_none());
+        let mut bytes = Vec::with_capacity(self.len() * mem::size_of::<f32>());
+        for index in 0..self.len() {
+            let value = self.get(index).unwrap_or(Value::Int(0));
+            let parameter = &parameters[index % parameters.len()];
+
+            if delimited {
+                if index > 0 {
+                    bytes.push(b',');
                 }
+                bytes.extend(value.as_f64().to_string().as_bytes());
+                continue;
+            }
+
+            encode_value(
+                &mut bytes,
+                value,
+                parameter.data_type.unwrap_or(data_type),
+                byteord,
+                parameter,
+            )?;
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Append a single value in the given type, using $PnB for integers and
+/// fixed-width ASCII.
+pub(crate) fn encode_value(
+    bytes: &mut Vec<u8>,
+    value: Value,
+    data_type: DataType,
+    byteord: &Byteord,
+    parameter: &Parameter,
+) -> Result<()> {
+    let offset = bytes.len() as u64;
+    let overflow = || FcsError::InvalidData {
+        offset,
+        value: value.as_f64().to_string(),
+    };
 
-                Ok(Data::Int(wrt))
+    match data_type {
+        DataType::Ascii => {
+            let width = parameter
+                .bits
+                .filter(|bits| *bits > 0)
+                .ok_or_else(|| invalid_bits(parameter))? as usize;
+            let text = format!("{:>width$}", value.as_u64(), width = width);
+            if text.len() > width {
+                return Err(overflow());
             }
-            DataType::Float => {
-                let chunks = bytes.chunks(mem::size_of::<f32>());
-
-                let mut wrt: Vec<f32> = vec![];
-                for chunk in chunks {
-                    let mut rdr = std::io::Cursor::new(chunk);
-                    wrt.push(match byteord {
-                        Byteord::BigEndian(_) => rdr.read_f32::<BigEndian>()?,
-                        Byteord::LittleEndian(_) => rdr.read_f32::<LittleEndian>()?,
-                    });
+            bytes.extend(text.as_bytes());
+        }
+        DataType::Int => {
+            let width = match parameter.bits {
+                Some(bits) if bits.is_multiple_of(8) && (8..=64).contains(&bits) => {
+                    bits as usize / 8
                 }
+                _ => return Err(invalid_bits(parameter)),
+            };
+            let value = value.as_u64();
+            if width < 8 && value >> (width * 8) != 0 {
+                return Err(overflow());
+            }
+            match byteord {
+                Byteord::BigEndian(_) => bytes.write_uint::<BigEndian>(value, width)?,
+                Byteord::LittleEndian(_) => bytes.write_uint::<LittleEndian>(value, width)?,
+            }
+        }
+        DataType::Float => match byteord {
+            Byteord::BigEndian(_) => bytes.write_f32::<BigEndian>(value.as_f64() as f32)?,
+            Byteord::LittleEndian(_) => bytes.write_f32::<LittleEndian>(value.as_f64() as f32)?,
+        },
+        DataType::Double => match byteord {
+            Byteord::BigEndian(_) => bytes.write_f64::<BigEndian>(value.as_f64())?,
+            Byteord::LittleEndian(_) => bytes.write_f64::<LittleEndian>(value.as_f64())?,
+        },
+    }
+
+    Ok(())
+}
+
+/// Width in bits and value mask of an integer parameter.
+#[derive(Clone, Copy)]
+struct IntColumn {
+    bits: u32,
+    mask: u64,
+}
+
+impl IntColumn {
+    fn new(parameter: &Parameter) -> Result<Self> {
+        let bits = match parameter.bits {
+            Some(bits) if (1..=64).contains(&bits) => bits,
+            _ => return Err(invalid_bits(parameter)),
+        };
+
+        // Values only use the bits below the next power of two at or above
+        // $PnR; anything higher up is instrument-specific flags.
+        let width_mask = u64::MAX >> (64 - bits);
+        let range_mask = match parameter.range.end {
+            0 => u64::MAX,
+            range => range
+                .checked_next_power_of_two()
+                .map_or(u64::MAX, |r| r - 1),
+        };
 
-                Ok(Data::Float(wrt))
+        Ok(IntColumn {
+            bits,
+            mask: width_mask & range_mask,
+        })
+    }
+
+    /// Width in bytes, for parameters aligned on byte boundaries.
+    fn width(&self) -> Option<usize> {
+        self.bits
+            .is_multiple_of(8)
+            .then_some(self.bits as usize / 8)
+    }
+
+    fn read(&self, chunk: &[u8], byteord: &Byteord) -> u64 {
+        let value = match byteord {
+            Byteord::BigEndian(_) => BigEndian::read_uint(chunk, chunk.len()),
+            Byteord::LittleEndian(_) => LittleEndian::read_uint(chunk, chunk.len()),
+        };
+        value & self.mask
+    }
+}
+
+/// Decode unsigned integer events, each parameter using its own $PnB.
+///
+/// Parameters whose width is not a multiple of 8 are packed back to back
+/// as a bit stream, most significant bit first, as FCS 2.0 describes.
+fn decode_int(bytes: &[u8], byteord: Byteord, parameters: &[Parameter]) -> Result<Vec<u64>> {
+    let columns = parameters
+        .iter()
+        .map(IntColumn::new)
+        .collect::<Result<Vec<_>>>()?;
+    if columns.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let widths = columns
+        .iter()
+        .map(IntColumn::width)
+        .collect::<Option<Vec<_>>>();
+
+    match widths {
+        Some(widths) => {
+            let events = decode(bytes, widths.iter().sum(), |event| {
+                let mut rest = event;
+                columns
+                    .iter()
+                    .zip(&widths)
+                    .map(|(column, &width)| {
+                        let (chunk, tail) = rest.split_at(width);
+                        rest = tail;
+                        column.read(chunk, &byteord)
+                    })
+                    .collect::<Vec<_>>()
+            })?;
+
+            Ok(events.into_iter().flatten().collect())
+        }
+        None => {
+            let event_bits: u64 = columns.iter().map(|c| c.bits as u64).sum();
+            let events = bytes.len() as u64 * 8 / event_bits;
+
+            let mut reader = BitReader::new(bytes);
+            let mut values = Vec::with_capacity((events as usize) * columns.len());
+            for _ in 0..events {
+                for column in &columns {
+                    values.push(reader.read(column.bits) & column.mask);
+                }
             }
-            DataType::Double => {
-                let chunks = bytes.chunks(mem::size_of::<f64>());
-
-                let mut wrt: Vec<f64> = vec![];
-                for chunk in chunks {
-                    let mut rdr = std::io::Cursor::new(chunk);
-                    wrt.push(match byteord {
-                        Byteord::BigEndian(_) => rdr.read_f64::<BigEndian>()?,
-                        Byteord::LittleEndian(_) => rdr.read_f64::<LittleEndian>()?,
-                    });
+
+            Ok(values)
+        }
+    }
+}
+
+/// Reads big-endian bit fields of arbitrary width from a byte slice.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        BitReader { bytes, position: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> u64 {
+        let mut value = 0u64;
+        for _ in 0..bits {
+            let byte = self.bytes[self.position / 8];
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u64;
+            self.position += 1;
+        }
+        value
+    }
+}
+
+/// Decode ASCII values, either delimited when every $PnB is `*` or
+/// fixed-width with $PnB giving the number of characters.
+fn decode_ascii(bytes: &[u8], parameters: &[Parameter]) -> Result<Vec<f64>> {
+    if parameters.iter().all(|p| p.bits.is_none()) {
+        let mut values = vec![];
+        let mut start = 0;
+        for (end, byte) in bytes.iter().chain(b" ").enumerate() {
+            if matches!(byte, b' ' | b'\t' | b',' | b'\r' | b'\n') {
+                if start < end {
+                    values.push(parse_ascii(&bytes[start..end], start)?);
                 }
+                start = end + 1;
+            }
+        }
+
+        return Ok(values);
+    }
 
-                Ok(Data::Double(wrt))
+    let widths = parameters
+        .iter()
+        .map(|p| match p.bits {
+            Some(bits) if bits > 0 => Ok(bits as usize),
+            _ => Err(invalid_bits(p)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let event_width: usize = widths.iter().sum();
+    if event_width == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut values = Vec::with_capacity(bytes.len() / event_width * widths.len());
+    let mut offset = 0;
+    for event in bytes.chunks(event_width) {
+        // Trailing line breaks or padding after the last event are not data.
+        if event.iter().all(u8::is_ascii_whitespace) {
+            break;
+        }
+
+        let mut rest = event;
+        for &width in &widths {
+            if rest.len() < width {
+                return Err(FcsError::TruncatedSegment {
+                    offset: offset as u64,
+                    expected: width as u64,
+                    found: rest.len() as u64,
+                });
             }
+
+            let (chunk, tail) = rest.split_at(width);
+            values.push(parse_ascii(chunk, offset)?);
+            offset += width;
+            rest = tail;
+        }
+    }
+
+    Ok(values)
+}
+
+fn parse_ascii(chunk: &[u8], offset: usize) -> Result<f64> {
+    let value = String::from_utf8_lossy(chunk);
+    value.trim().parse().map_err(|_| FcsError::InvalidData {
+        offset: offset as u64,
+        value: value.into_owned(),
+    })
+}
+
+pub(crate) fn invalid_bits(parameter: &Parameter) -> FcsError {
+    FcsError::InvalidKeyword {
+        keyword: ParameterKeyword::Bits.of(parameter.index),
+        value: parameter
+            .bits
+            .map_or_else(|| "*".to_string(), |bits| bits.to_string()),
+    }
+}
+
+/// Decode events whose parameters each carry their own data type.
+fn decode_mixed(
+    bytes: &[u8],
+    data_type: DataType,
+    byteord: Byteord,
+    parameters: &[Parameter],
+) -> Result<Vec<Value>> {
+    let layout = parameters
+        .iter()
+        .map(|p| Field::new(p, data_type))
+        .collect::<Result<Vec<_>>>()?;
+
+    let event_width: usize = layout.iter().map(|field| field.width).sum();
+    if event_width == 0 {
+        return Ok(vec![]);
+    }
+
+    let events = decode(bytes, event_width, |event| {
+        let mut values = Vec::with_capacity(layout.len());
+        let mut rest = event;
+        for field in &layout {
+            let (chunk, tail) = rest.split_at(field.width);
+            rest = tail;
+            values.push(field.read(chunk, &byteord));
+        }
+        values
+    })?;
+
+    Ok(events.into_iter().flatten().collect())
+}
+
+/// Type, width in bytes and, for integers, mask of one value of an event
+/// whose values all start on byte boundaries.
+#[derive(Clone, Copy)]
+pub(crate) struct Field {
+    data_type: DataType,
+    pub(crate) width: usize,
+    int: Option<IntColumn>,
+}
+
+impl Field {
+    /// Field of the parameter, which fails unless every value of the segment
+    /// starts on a byte boundary: ASCII and packed integers are not
+    /// supported.
+    pub(crate) fn new(parameter: &Parameter, data_type: DataType) -> Result<Self> {
+        let data_type = parameter.data_type.unwrap_or(data_type);
+        let (width, int) = match data_type {
+            DataType::Int => {
+                let column = IntColumn::new(parameter)?;
+                let width = column.width().ok_or_else(|| invalid_bits(parameter))?;
+                (width, Some(column))
+            }
+            DataType::Float => (mem::size_of::<f32>(), None),
+            DataType::Double => (mem::size_of::<f64>(), None),
+            DataType::Ascii => return Err(FcsError::UnsupportedDataType("A".into())),
+        };
+
+        Ok(Field {
+            data_type,
+            width,
+            int,
+        })
+    }
+
+    pub(crate) fn read(&self, chunk: &[u8], byteord: &Byteord) -> Value {
+        match (self.data_type, byteord, &self.int) {
+            (DataType::Int, _, Some(column)) => Value::Int(column.read(chunk, byteord)),
+            (DataType::Double, Byteord::BigEndian(_), _) => {
+                Value::Double(BigEndian::read_f64(chunk))
+            }
+            (DataType::Double, Byteord::LittleEndian(_), _) => {
+                Value::Double(LittleEndian::read_f64(chunk))
+            }
+            (_, Byteord::BigEndian(_), _) => Value::Float(BigEndian::read_f32(chunk)),
+            (_, Byteord::LittleEndian(_), _) => Value::Float(LittleEndian::read_f32(chunk)),
+        }
+    }
+}
+
+/// View over the values of one parameter in a list mode DATA segment,
+/// decoded only when accessed, for the layouts `Field::new` accepts.
+pub struct Column<'a> {
+    bytes: &'a [u8],
+    byteord: Byteord,
+    field: Field,
+    offset: usize,
+    stride: usize,
+}
+
+impl<'a> Column<'a> {
+    /// View over the parameter at index, starting from 0.
+    pub fn new(
+        bytes: &'a [u8],
+        data_type: DataType,
+        byteord: &Byteord,
+        parameters: &[Parameter],
+        index: usize,
+    ) -> Result<Self> {
+        let layout = parameters
+            .iter()
+            .map(|p| Field::new(p, data_type))
+            .collect::<Result<Vec<_>>>()?;
+        let field = *layout.get(index).ok_or_else(|| {
+            FcsError::MissingKeyword(ParameterKeyword::ShortName.of(index as u32 + 1))
+        })?;
+
+        let stride: usize = layout.iter().map(|field| field.width).sum();
+        let remainder = bytes.len() % stride;
+        if remainder != 0 {
+            return Err(FcsError::TruncatedSegment {
+                offset: (bytes.len() - remainder) as u64,
+                expected: stride as u64,
+                found: remainder as u64,
+            });
+        }
+
+        Ok(Column {
+            bytes,
+            byteord: byteord.clone(),
+            field,
+            offset: layout[..index].iter().map(|field| field.width).sum(),
+            stride,
+        })
+    }
+
+    /// Number of events.
+    pub fn len(&self) -> usize {
+        self.bytes.len() / self.stride
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Value of the parameter in the event at index.
+    pub fn get(&self, event: usize) -> Option<Value> {
+        let start = event.checked_mul(self.stride)? + self.offset;
+        let chunk = self.bytes.get(start..start + self.field.width)?;
+        Some(self.field.read(chunk, &self.byteord))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Value> + '_ {
+        (0..self.len()).filter_map(|event| self.get(event))
+    }
+}
+
+/// Split bytes into width-sized chunks and decode each one, rejecting a
+/// trailing partial value.
+fn decode<T, F>(bytes: &[u8], width: usize, read: F) -> Result<Vec<T>>
+where
+    F: Fn(&[u8]) -> T,
+{
+    let chunks = bytes.chunks_exact(width);
+    let remainder = chunks.remainder().len();
+    if remainder != 0 {
+        return Err(FcsError::TruncatedSegment {
+            offset: (bytes.len() - remainder) as u64,
+            expected: width as u64,
+            found: remainder as u64,
+        });
+    }
+
+    Ok(chunks.map(read).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::text::Text;
+
+    use super::{Byteord, Column, Data, DataType, Value};
+
+    #[test]
+    fn it_decodes_mixed_data_types() {
+        let text = Text::new(
+            b"\\$PAR\\2\\$P1B\\16\\$P1N\\Time\\$P1R\\1024\\$P1DATATYPE\\I\\$P2B\\32\\$P2N\\FSC-A\\$P2R\\1024\\",
+        )
+        .unwrap();
+        let mut bytes = vec![];
+        for (time, fsc) in [(258u16, 1.5f32), (3, -2.0)] {
+            bytes.extend(time.to_be_bytes());
+            bytes.extend(fsc.to_be_bytes());
+        }
+
+        let data = Data::new(
+            &bytes,
+            DataType::Float,
+            Byteord::BigEndian("4,3,2,1".into()),
+            &text.parameters().unwrap(),
+        )
+        .unwrap();
+
+        match data {
+            Data::Mixed(values) => assert_eq!(
+                values,
+                vec![
+                    Value::Int(258),
+                    Value::Float(1.5),
+                    Value::Int(3),
+                    Value::Float(-2.0)
+                ]
+            ),
+            other => panic!("unexpected data: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_views_a_column_without_decoding_the_rest() {
+        let text = Text::new(
+            b"\\$PAR\\2\\$P1B\\16\\$P1N\\Time\\$P1R\\1024\\$P2B\\32\\$P2N\\FSC-A\\$P2R\\1024\\",
+        )
+        .unwrap();
+        let mut bytes = vec![];
+        for (time, fsc) in [(258u16, 10u32), (3, 20)] {
+            bytes.extend(time.to_le_bytes());
+            bytes.extend(fsc.to_le_bytes());
+        }
+
+        let column = Column::new(
+            &bytes,
+            DataType::Int,
+            &Byteord::LittleEndian("1,2".into()),
+            &text.parameters().unwrap(),
+            1,
+        )
+        .unwrap();
+
+        assert_eq!(column.len(), 2);
+        assert_eq!(
+            column.iter().collect::<Vec<_>>(),
+            vec![Value::Int(10), Value::Int(20)]
+        );
+        assert_eq!(column.get(2), None);
+    }
+
+    #[test]
+    fn it_masks_16_bit_integers_to_the_range() {
+        let text = Text::new(
+            b"\\$PAR\\2\\$P1B\\16\\$P1N\\FSC-H\\$P1R\\1024\\$P2B\\16\\$P2N\\SSC-H\\$P2R\\1000\\",
+        )
+        .unwrap();
+        let bytes = [0xff, 0xff, 0x01, 0x84];
+
+        let data = Data::new(
+            &bytes,
+            DataType::Int,
+            Byteord::LittleEndian("1,2".into()),
+            &text.parameters().unwrap(),
+        )
+        .unwrap();
+
+        match data {
+            Data::Int(values) => assert_eq!(values, vec![1023, 0x0001]),
+            other => panic!("unexpected data: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_decodes_packed_integers() {
+        let text = Text::new(
+            b"\\$PAR\\2\\$P1B\\10\\$P1N\\FSC-H\\$P1R\\1024\\$P2B\\12\\$P2N\\SSC-H\\$P2R\\4096\\",
+        )
+        .unwrap();
+        // 1000 as 10 bits followed by 4000 as 12 bits, padded to 3 bytes.
+        let bits = (1000u32 << 14) | (4000 << 2);
+        let bytes = &bits.to_be_bytes()[1..];
+
+        let data = Data::new(
+            bytes,
+            DataType::Int,
+            Byteord::BigEndian("4,3,2,1".into()),
+            &text.parameters().unwrap(),
+        )
+        .unwrap();
+
+        match data {
+            Data::Int(values) => assert_eq!(values, vec![1000, 4000]),
+            other => panic!("unexpected data: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_decodes_fixed_width_ascii() {
+        let text = Text::new(
+            b"\\$PAR\\2\\$P1B\\4\\$P1N\\FSC-H\\$P1R\\1024\\$P2B\\3\\$P2N\\SSC-H\\$P2R\\1000\\",
+        )
+        .unwrap();
+
+        let data = Data::new(
+            b"1023 12  07999",
+            DataType::Ascii,
+            Byteord::BigEndian("4,3,2,1".into()),
+            &text.parameters().unwrap(),
+        )
+        .unwrap();
+
+        match data {
+            Data::Ascii(values) => assert_eq!(values, vec![1023.0, 12.0, 7.0, 999.0]),
+            other => panic!("unexpected data: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_decodes_delimited_ascii() {
+        let text = Text::new(
+            b"\\$PAR\\2\\$P1B\\*\\$P1N\\FSC-H\\$P1R\\1024\\$P2B\\*\\$P2N\\SSC-H\\$P2R\\1000\\",
+        )
+        .unwrap();
+
+        let data = Data::new(
+            b"1023,12\r\n7\t999\r\n",
+            DataType::Ascii,
+            Byteord::BigEndian("4,3,2,1".into()),
+            &text.parameters().unwrap(),
+        )
+        .unwrap();
+
+        match data {
+            Data::Ascii(values) => assert_eq!(values, vec![1023.0, 12.0, 7.0, 999.0]),
+            other => panic!("unexpected data: {:?}", other),
         }
     }
 }
@@ -0,0 +1,341 @@
+use std::{fmt, time::Duration};
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+/// Calendar date, as written in $DATE and other date keywords.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+/// Ways dates are written in TEXT.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateFormat {
+    /// dd-mmm-yyyy, as FCS 3.x requires for $DATE.
+    Fcs,
+    /// dd-mmm-yy, as FCS 2.0 files often have.
+    FcsShortYear,
+    /// yyyy-mm-dd, as in ISO 8601 date-times.
+    Iso,
+    /// mm/dd/yyyy, common in vendor keywords.
+    Us,
+}
+
+impl Date {
+    /// A date, or None if the day does not exist.
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+            2 => 28,
+            _ => return None,
+        };
+
+        (1..=days)
+            .contains(&day)
+            .then_some(Date { year, month, day })
+    }
+
+    /// Parse a date making up the whole value.
+    pub fn parse(value: &str) -> Option<Self> {
+        match Date::parse_prefix(value)? {
+            (date, _, rest) if rest.trim().is_empty() => Some(date),
+            _ => None,
+        }
+    }
+
+    /// Parse a date at the start of value, returning it with the format it
+    /// is written in and the rest of value.
+    pub fn parse_prefix(value: &str) -> Option<(Self, DateFormat, &str)> {
+        let value = value.trim_start();
+        let field = |start: usize, length: usize| -> Option<u32> {
+            let digits = value.get(start..start + length)?;
+            match digits.bytes().all(|b| b.is_ascii_digit()) {
+                true => digits.parse().ok(),
+                false => None,
+            }
+        };
+        let month = |start: usize| -> Option<u32> {
+            let name = value.get(start..start + 3)?.to_ascii_uppercase();
+            MONTHS.iter().position(|m| *m == name).map(|m| m as u32 + 1)
+        };
+        let separators =
+            |at: &[(usize, u8)]| at.iter().all(|(i, b)| value.as_bytes().get(*i) == Some(b));
+        let ends = |length: usize| {
+            !value
+                .as_bytes()
+                .get(length)
+                .is_some_and(|b| b.is_ascii_digit())
+        };
+
+        let dashed = separators(&[(2, b'-'), (6, b'-')]);
+
+        let (date, format, length) = if dashed && field(7, 4).is_some() && ends(11) {
+            let date = Date::new(field(7, 4)? as i32, month(3)?, field(0, 2)?);
+            (date, DateFormat::Fcs, 11)
+        } else if dashed && ends(9) {
+            // Two-digit years pivot at 1970.
+            let year = match field(7, 2)? as i32 {
+                year if year < 70 => 2000 + year,
+                year => 1900 + year,
+            };
+            (
+                Date::new(year, month(3)?, field(0, 2)?),
+                DateFormat::FcsShortYear,
+                9,
+            )
+        } else if separators(&[(4, b'-'), (7, b'-')]) && ends(10) {
+            let date = Date::new(field(0, 4)? as i32, field(5, 2)?, field(8, 2)?);
+            (date, DateFormat::Iso, 10)
+        } else if separators(&[(2, b'/'), (5, b'/')]) && ends(10) {
+            let date = Date::new(field(6, 4)? as i32, field(0, 2)?, field(3, 2)?);
+            (date, DateFormat::Us, 10)
+        } else {
+            return None;
+        };
+
+        Some((date?, format, &value[length..]))
+    }
+
+    /// Days since 1970-01-01.
+    pub fn days(&self) -> i64 {
+        // Howard Hinnant's days_from_civil.
+        let year = self.year as i64 - (self.month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = (self.month as i64 + 9) % 12;
+        let day_of_year = (153 * month + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    /// Date a number of days after 1970-01-01, or None when its year does
+    /// not fit.
+    pub fn from_days(days: i64) -> Option<Self> {
+        let days = days.checked_add(719_468)?;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+
+        Some(Date {
+            year: i32::try_from(year_of_era + era * 400 + (month <= 2) as i64).ok()?,
+            month: month as u32,
+            day: day as u32,
+        })
+    }
+
+    /// Date a number of days later, or None when its year does not fit.
+    pub fn add_days(&self, days: i64) -> Option<Self> {
+        Date::from_days(self.days().checked_add(days)?)
+    }
+
+    pub fn format(&self, format: DateFormat) -> String {
+        let month = MONTHS[self.month as usize - 1];
+        match format {
+            DateFormat::Fcs => format!("{:02}-{}-{:04}", self.day, month, self.year),
+            DateFormat::FcsShortYear => {
+                format!("{:02}-{}-{:02}", self.day, month, self.year.rem_euclid(100))
+            }
+            DateFormat::Iso => format!("{:04}-{:02}-{:02}", self.year, self.month, self.day),
+            DateFormat::Us => format!("{:02}/{:02}/{:04}", self.month, self.day, self.year),
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(DateFormat::Fcs))
+    }
+}
+
+/// Time of day, as written in $BTIM and $ETIM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Time {
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanosecond: u32,
+}
+
+impl Time {
+    /// Parse hh:mm:ss, with either FCS 3.1 fractional seconds, hh:mm:ss.cc,
+    /// or FCS 2.0 sixtieths of a second, hh:mm:ss:tt.
+    pub fn parse(value: &str) -> Option<Self> {
+        let number = |field: &str| -> Option<u32> {
+            match !field.is_empty() && field.bytes().all(|b| b.is_ascii_digit()) {
+                true => field.parse().ok(),
+                false => None,
+            }
+        };
+
+        let fields: Vec<&str> = value.trim().split(':').collect();
+        let (hour, minute, second, nanosecond) = match fields[..] {
+            [hour, minute, second] => {
+                let (second, fraction) = second.split_once('.').unwrap_or((second, ""));
+                let nanosecond = match fraction {
+                    "" => 0,
+                    fraction => {
+                        number(fraction)?;
+                        // Keep nanosecond precision, padding or cutting digits.
+                        let digits: String = fraction
+                            .chars()
+                            .chain("000000000".chars())
+                            .take(9)
+                            .collect();
+                        digits.parse().ok()?
+                    }
+                };
+                (number(hour)?, number(minute)?, number(second)?, nanosecond)
+            }
+            [hour, minute, second, sixtieths] => {
+                let sixtieths = number(sixtieths).filter(|t| *t < 60)?;
+                let nanosecond = (sixtieths as u64 * 1_000_000_000 + 30) / 60;
+                (
+                    number(hour)?,
+                    number(minute)?,
+                    number(second)?,
+                    nanosecond as u32,
+                )
+            }
+            _ => return None,
+        };
+
+        (hour < 24 && minute < 60 && second < 60).then_some(Time {
+            hour,
+            minute,
+            second,
+            nanosecond,
+        })
+    }
+
+    /// Time elapsed since midnight.
+    pub fn since_midnight(&self) -> Duration {
+        Duration::new(
+            (self.hour * 3600 + self.minute * 60 + self.second) as u64,
+            self.nanosecond,
+        )
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+        match self.nanosecond {
+            0 => Ok(()),
+            nanosecond => write!(f, ".{:02}", nanosecond / 10_000_000),
+        }
+    }
+}
+
+/// Date and time of day, as written in $LAST_MODIFIED.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+}
+
+impl DateTime {
+    /// Parse dd-mmm-yyyy hh:mm:ss[.cc].
+    pub fn parse(value: &str) -> Option<Self> {
+        let (date, _, rest) = Date::parse_prefix(value)?;
+        let rest = rest.strip_prefix([' ', '-'])?;
+
+        Some(DateTime {
+            date,
+            time: Time::parse(rest)?,
+        })
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.date, self.time)
+    }
+}
+
+/// Time from begin to end, assuming an acquisition that ends earlier in
+/// the day than it began went past midnight.
+pub fn duration(begin: Time, end: Time) -> Duration {
+    let (begin, end) = (begin.since_midnight(), end.since_midnight());
+    match end.checked_sub(begin) {
+        Some(duration) => duration,
+        None => end + Duration::from_secs(24 * 3600) - begin,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{duration, Date, DateFormat, DateTime, Time};
+
+    #[test]
+    fn it_parses_the_formats_found_in_text() {
+        let date = Date::new(2007, 7, 17).unwrap();
+
+        assert_eq!(
+            Date::parse_prefix("17-JUL-2007"),
+            Some((date, DateFormat::Fcs, ""))
+        );
+        assert_eq!(
+            Date::parse_prefix("17-Jul-07"),
+            Some((date, DateFormat::FcsShortYear, ""))
+        );
+        assert_eq!(
+            Date::parse_prefix("2007-07-17T15:36:28"),
+            Some((date, DateFormat::Iso, "T15:36:28"))
+        );
+        assert_eq!(
+            Date::parse_prefix("07/17/2007"),
+            Some((date, DateFormat::Us, ""))
+        );
+        assert_eq!(Date::parse_prefix("31-FEB-2007"), None);
+        assert_eq!(Date::parse_prefix("19.6"), None);
+    }
+
+    #[test]
+    fn it_shifts_across_months_and_leap_years() {
+        let date = Date::new(2000, 2, 27).unwrap();
+
+        assert_eq!(date.add_days(2), Date::new(2000, 2, 29));
+        assert_eq!(date.add_days(3), Date::new(2000, 3, 1));
+        assert_eq!(date.add_days(-58), Date::new(1999, 12, 31));
+        assert_eq!(date.add_days(i64::MAX), None);
+        assert_eq!(date.add_days(i64::MIN), None);
+        assert_eq!(Date::new(1970, 1, 1).unwrap().days(), 0);
+    }
+
+    #[test]
+    fn it_parses_times_with_fractions_and_sixtieths() {
+        let time = Time::parse("15:36:28").unwrap();
+        assert_eq!(time.since_midnight(), Duration::from_secs(56188));
+
+        assert_eq!(Time::parse("15:36:28.25").unwrap().nanosecond, 250_000_000);
+        assert_eq!(Time::parse("15:36:28:30").unwrap().nanosecond, 500_000_000);
+        assert_eq!(Time::parse("15:36:28:60"), None);
+        assert_eq!(Time::parse("25:00:00"), None);
+
+        let modified = DateTime::parse("17-JUL-2007 16:04:38.50").unwrap();
+        assert_eq!(modified.date, Date::new(2007, 7, 17).unwrap());
+        assert_eq!(modified.time.to_string(), "16:04:38.50");
+    }
+
+    #[test]
+    fn it_computes_durations_past_midnight() {
+        let begin = Time::parse("23:59:00").unwrap();
+        let end = Time::parse("00:01:30").unwrap();
+
+        assert_eq!(duration(begin, end), Duration::from_secs(150));
+    }
+}
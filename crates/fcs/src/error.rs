@@ -0,0 +1,162 @@
+use std::{error, fmt, io, result};
+
+pub type Result<T> = result::Result<T, FcsError>;
+
+#[derive(Debug)]
+pub enum FcsError {
+    /// Underlying I/O failure while reading or writing a file.
+    Io(io::Error),
+    /// A HEADER field, located at `offset`, could not be parsed.
+    InvalidHeader {
+        field: &'static str,
+        offset: u64,
+        value: String,
+    },
+    /// A segment whose end precedes its start.
+    InvalidSegment { start: u64, end: u64 },
+    /// A segment that ends before the number of bytes it declares.
+    TruncatedSegment {
+        offset: u64,
+        expected: u64,
+        found: u64,
+    },
+    /// A segment offset in HEADER that disagrees with its TEXT keyword.
+    OffsetMismatch {
+        keyword: String,
+        header: u64,
+        text: u64,
+    },
+    /// TEXT segment bytes that cannot be decoded.
+    InvalidText { offset: u64 },
+    /// A keyword left without a value at the end of TEXT.
+    UnpairedKeyword(String),
+    /// A keyword the standard requires is absent from TEXT.
+    MissingKeyword(String),
+    /// A keyword whose value cannot be interpreted.
+    InvalidKeyword { keyword: String, value: String },
+    /// A `$` keyword that cannot be edited: either the standard does not
+    /// define it or it describes the layout of DATA.
+    ReservedKeyword(String),
+    /// A DATA value, located at `offset` within the segment, that cannot be
+    /// parsed.
+    InvalidData { offset: u64, value: String },
+    /// The CRC stored after the last segment is not 8 decimal digits.
+    InvalidCrc(String),
+    /// The CRC stored after the last segment disagrees with the contents.
+    CrcMismatch { stored: u16, computed: u16 },
+    /// An event that does not hold one value per parameter.
+    InvalidEvent {
+        event: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A spillover matrix that has no inverse.
+    SingularMatrix,
+    /// Compensation requested on data that was already compensated.
+    AlreadyCompensated,
+    /// A parameter named by neither $PnN nor $PnS.
+    UnknownParameter(String),
+    /// A compensation control with too few events to estimate spillover,
+    /// named by its parameter.
+    NotEnoughEvents(String),
+    /// $DATATYPE value this crate cannot decode.
+    UnsupportedDataType(String),
+    /// $BYTEORD value this crate cannot decode.
+    UnsupportedByteord(String),
+}
+
+impl fmt::Display for FcsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FcsError::Io(err) => write!(f, "i/o error: {}", err),
+            FcsError::InvalidHeader {
+                field,
+                offset,
+                value,
+            } => write!(
+                f,
+                "invalid HEADER {} at byte {}: {:?}",
+                field, offset, value
+            ),
+            FcsError::InvalidSegment { start, end } => {
+                write!(f, "invalid segment: end {} precedes start {}", end, start)
+            }
+            FcsError::TruncatedSegment {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "truncated segment at byte {}: expected {} bytes, found {}",
+                offset, expected, found
+            ),
+            FcsError::OffsetMismatch {
+                keyword,
+                header,
+                text,
+            } => write!(
+                f,
+                "HEADER offset {} disagrees with {} {}",
+                header, keyword, text
+            ),
+            FcsError::InvalidText { offset } => {
+                write!(f, "invalid TEXT segment at byte {}", offset)
+            }
+            FcsError::UnpairedKeyword(keyword) => {
+                write!(f, "keyword {} has no value", keyword)
+            }
+            FcsError::MissingKeyword(keyword) => write!(f, "missing keyword {}", keyword),
+            FcsError::InvalidKeyword { keyword, value } => {
+                write!(f, "invalid value for {}: {:?}", keyword, value)
+            }
+            FcsError::ReservedKeyword(keyword) => {
+                write!(f, "reserved keyword {} cannot be edited", keyword)
+            }
+            FcsError::InvalidData { offset, value } => {
+                write!(f, "invalid DATA value at byte {}: {:?}", offset, value)
+            }
+            FcsError::InvalidCrc(crc) => write!(f, "invalid CRC {:?}", crc),
+            FcsError::CrcMismatch { stored, computed } => write!(
+                f,
+                "CRC mismatch: stored {:05}, computed {:05}",
+                stored, computed
+            ),
+            FcsError::InvalidEvent {
+                event,
+                expected,
+                found,
+            } => write!(
+                f,
+                "event {} has {} values, expected {}",
+                event, found, expected
+            ),
+            FcsError::SingularMatrix => write!(f, "spillover matrix is singular"),
+            FcsError::AlreadyCompensated => write!(f, "data is already compensated"),
+            FcsError::UnknownParameter(name) => write!(f, "no parameter named {:?}", name),
+            FcsError::NotEnoughEvents(control) => {
+                write!(f, "not enough events in the {} control", control)
+            }
+            FcsError::UnsupportedDataType(data_type) => {
+                write!(f, "unsupported $DATATYPE {:?}", data_type)
+            }
+            FcsError::UnsupportedByteord(byteord) => {
+                write!(f, "unsupported $BYTEORD {:?}", byteord)
+            }
+        }
+    }
+}
+
+impl error::Error for FcsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            FcsError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FcsError {
+    fn from(err: io::Error) -> Self {
+        FcsError::Io(err)
+    }
+}
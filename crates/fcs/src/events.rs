@@ -0,0 +1,150 @@
+use crate::{
+    data::Data,
+    error::{FcsError, Result},
+    keywords::RequiredKeyword,
+    text::{Parameter, Text},
+};
+
+/// List mode DATA seen as a matrix of $TOT events by $PAR parameters, with
+/// every value converted to f64.
+pub struct Events<'a> {
+    data: &'a Data,
+    parameters: Vec<Parameter>,
+    events: usize,
+}
+
+impl<'a> Events<'a> {
+    /// Matrix over the data decoded from the DATA segment TEXT describes.
+    pub fn new(data: &'a Data, text: &Text) -> Result<Self> {
+        text.require_list_mode()?;
+        let parameters = text.parameters()?;
+        let events = text.total_events()? as usize;
+
+        if events.checked_mul(parameters.len()) != Some(data.len()) {
+            return Err(FcsError::InvalidKeyword {
+                keyword: RequiredKeyword::Tot.to_string(),
+                value: events.to_string(),
+            });
+        }
+
+        Ok(Events {
+            data,
+            parameters,
+            events,
+        })
+    }
+
+    /// Number of events, $TOT.
+    pub fn len(&self) -> usize {
+        self.events
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events == 0
+    }
+
+    /// The parameters, in column order.
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// Column of the parameter whose $PnN, or failing that $PnS, is name.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.parameters
+            .iter()
+            .position(|p| p.short_name == name)
+            .or_else(|| {
+                self.parameters
+                    .iter()
+                    .position(|p| p.name.as_deref() == Some(name))
+            })
+    }
+
+    /// Value of a parameter in an event, both starting from 0.
+    pub fn get(&self, event: usize, parameter: usize) -> Option<f64> {
+        if event >= self.events || parameter >= self.parameters.len() {
+            return None;
+        }
+
+        self.data
+            .get(event * self.parameters.len() + parameter)
+            .map(|value| value.as_f64())
+    }
+
+    /// Every value of an event.
+    pub fn row(&self, event: usize) -> Option<Vec<f64>> {
+        (0..self.parameters.len())
+            .map(|parameter| self.get(event, parameter))
+            .collect()
+    }
+
+    /// Every value of a parameter.
+    pub fn column(&self, parameter: usize) -> Option<Vec<f64>> {
+        if parameter >= self.parameters.len() {
+            return None;
+        }
+
+        (0..self.events)
+            .map(|event| self.get(event, parameter))
+            .collect()
+    }
+
+    /// Every value of the parameter named by $PnN or $PnS.
+    pub fn column_by_name(&self, name: &str) -> Option<Vec<f64>> {
+        self.column(self.index_of(name)?)
+    }
+
+    /// Every value, event after event.
+    pub fn to_f64(&self) -> Vec<f64> {
+        (0..self.data.len())
+            .filter_map(|index| self.data.get(index))
+            .map(|value| value.as_f64())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        builder::{FcsBuilder, ParameterDefinition},
+        data::DataType,
+        error::FcsError,
+    };
+
+    #[test]
+    fn it_accesses_events_by_row_column_and_name() {
+        let fcs = FcsBuilder::new(DataType::Int)
+            .parameter(ParameterDefinition::new("FSC-H", 1024).bits(16))
+            .parameter(
+                ParameterDefinition::new("FL1-H", 1024)
+                    .bits(16)
+                    .label("CD3"),
+            )
+            .events([[10.0, 20.0], [30.0, 40.0], [50.0, 60.0]])
+            .build()
+            .unwrap();
+        let events = fcs.events().unwrap();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events.get(1, 1), Some(40.0));
+        assert_eq!(events.get(3, 0), None);
+        assert_eq!(events.row(2), Some(vec![50.0, 60.0]));
+        assert_eq!(events.column(0), Some(vec![10.0, 30.0, 50.0]));
+        assert_eq!(events.column_by_name("FL1-H"), events.column(1));
+        assert_eq!(events.column_by_name("CD3"), events.column(1));
+        assert_eq!(events.column_by_name("SSC-H"), None);
+        assert_eq!(events.to_f64(), vec![10.0, 20.0, 30.0, 40.0, 50.0, 60.0]);
+    }
+
+    #[test]
+    fn it_rejects_a_tot_that_disagrees_with_data() {
+        let mut fcs = FcsBuilder::new(DataType::Float)
+            .parameter(ParameterDefinition::new("FSC-A", 1024))
+            .events([[1.0], [2.0]])
+            .build()
+            .unwrap();
+        fcs.text.pairs.insert("$TOT".into(), "3".into());
+
+        assert!(matches!(fcs.events(), Err(FcsError::InvalidKeyword { .. })));
+    }
+}
@@ -1,20 +1,151 @@
-use crate::{data::Data, header::Header, text::Text};
+use std::io::Cursor;
+
+use crate::{
+    analysis::Analysis,
+    compensation::{SpilloverMatrix, COMPENSATED},
+    data::Data,
+    error::{FcsError, Result},
+    events::Events,
+    header::Header,
+    keywords::{OptionalKeyword, ParameterKeyword, RequiredKeyword},
+    text::Text,
+    traits::FcsRead,
+};
+
+/// HEADER, TEXT and ANALYSIS of a data set, read without its DATA.
+#[derive(Debug)]
+pub struct FcsMetadata {
+    pub header: Header,
+    pub text: Text,
+    pub analysis: Option<Analysis>,
+}
 
 #[derive(Debug)]
 pub struct Fcs {
     pub header: Header,
     pub data: Data,
     pub text: Text,
+    pub analysis: Option<Analysis>,
+    /// Spillover matrix whose inverse [`Fcs::compensate`] applied to `data`.
+    pub compensation: Option<SpilloverMatrix>,
+}
+
+impl Fcs {
+    /// Read the first data set of a file already held in memory.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Cursor::new(bytes).read_fcs()
+    }
+
+    /// Events as a matrix of $TOT rows by $PAR columns.
+    pub fn events(&self) -> Result<Events<'_>> {
+        Events::new(&self.data, &self.text)
+    }
+
+    /// Compensate fluorescence, applying the inverse of the spillover matrix
+    /// to the parameters it covers and leaving the others, such as scatter
+    /// and time, untouched. The matrix defaults to the one in TEXT.
+    ///
+    /// Data that is not already floating point becomes double precision,
+    /// with $DATATYPE and $PnB updated to match. The matrix is recorded in
+    /// the [`COMPENSATED`] keyword.
+    pub fn compensate(&mut self, matrix: Option<&SpilloverMatrix>) -> Result<()> {
+        if self.compensation.is_some() || self.text.get(COMPENSATED).is_some() {
+            return Err(FcsError::AlreadyCompensated);
+        }
+        let matrix = match matrix {
+            Some(matrix) => matrix.clone(),
+            None => self
+                .text
+                .spillover()?
+                .ok_or_else(|| FcsError::MissingKeyword(OptionalKeyword::Spillover.to_string()))?,
+        };
+        let inverse = matrix.inverse()?;
+
+        let events = self.events()?;
+        let parameters = events.parameters().len();
+        let columns = matrix.columns(events.parameters())?;
+
+        let mut values = events.to_f64();
+        for event in values.chunks_exact_mut(parameters) {
+            matrix.apply(&inverse, &columns, event);
+        }
+
+        self.data = match self.data {
+            Data::Float(_) => Data::Float(values.into_iter().map(|v| v as f32).collect()),
+            Data::Double(_) => Data::Double(values),
+            _ => {
+                // The layout keywords cannot be changed through Text::set.
+                let pairs = &mut self.text.pairs;
+                pairs.insert(RequiredKeyword::DataType.to_string(), "D".into());
+                for index in 1..=parameters as u32 {
+                    pairs.insert(ParameterKeyword::Bits.of(index), "64".into());
+                    pairs.remove(&ParameterKeyword::DataType.of(index));
+                }
+                Data::Double(values)
+            }
+        };
+        self.text.set(COMPENSATED, matrix.to_keyword())?;
+        self.compensation = Some(matrix);
+
+        Ok(())
+    }
+
+    /// Last byte of the last segment, relative to the start of the HEADER.
+    pub fn last_byte(&self) -> Result<u64> {
+        let supplemental = self.text.supplemental_offsets()?.map_or(0, |(_, end)| end);
+
+        Ok([
+            self.header.text_end,
+            self.header.data_end,
+            self.header.analysis_end.unwrap_or(0),
+            supplemental,
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or(0))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{fs::File, io, path::PathBuf};
+    use std::{fs::File, io::Cursor, path::PathBuf};
+
+    use crate::{
+        builder::{FcsBuilder, ParameterDefinition},
+        compensation::{SpilloverMatrix, COMPENSATED},
+        crc::{self, Crc},
+        data::{Data, DataType},
+        error::{FcsError, Result},
+        traits::{ByteRead, FcsRead, FcsWrite},
+    };
+
+    use super::Fcs;
 
-    use crate::traits::FcsRead;
+    /// Assemble a single data set of one 8-bit parameter.
+    fn dataset(events: &[u8], next: u64) -> Vec<u8> {
+        let text = format!(
+            "\\$TOT\\{}\\$PAR\\1\\$MODE\\L\\$DATATYPE\\I\\$BYTEORD\\1,2,3,4\\$NEXTDATA\\{:>8}\\$P1B\\8\\$P1N\\FSC-H\\$P1R\\256\\",
+            events.len(),
+            next
+        );
+        let text_end = 58 + text.len() - 1;
+        let mut bytes = format!(
+            "FCS3.0    {:>8}{:>8}{:>8}{:>8}{:>8}{:>8}",
+            58,
+            text_end,
+            text_end + 1,
+            text_end + events.len(),
+            0,
+            0
+        )
+        .into_bytes();
+        bytes.extend(text.as_bytes());
+        bytes.extend(events);
+        bytes
+    }
 
     #[test]
-    fn it_opens_a_file() -> io::Result<()> {
+    fn it_opens_a_file() -> Result<()> {
         dbg!(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs"));
         File::open(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs"))?;
 
@@ -22,15 +153,263 @@ mod tests {
     }
 
     #[test]
-    fn it_returns_data() -> io::Result<()> {
-        dbg!(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs"));
+    fn it_returns_data() -> Result<()> {
         let mut file =
             File::open(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs"))?;
 
-        let _fcs = file.read_fcs()?;
-        dbg!(&_fcs.text.pairs);
-        dbg!(_fcs.text.total_events());
-        assert_eq!(true, false);
+        let fcs = file.read_fcs()?;
+        assert_eq!(fcs.text.total_events()?, 65016);
+        assert_eq!(fcs.text.parameters_number()?, 16);
+        match fcs.data {
+            Data::Float(values) => assert_eq!(values.len(), 65016 * 16),
+            other => panic!("unexpected data: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_from_bytes() -> Result<()> {
+        let fcs = Fcs::from_bytes(&dataset(&[1, 2, 3], 0))?;
+
+        match fcs.data {
+            Data::Int(values) => assert_eq!(values, vec![1, 2, 3]),
+            other => panic!("unexpected data: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_metadata_without_data() -> Result<()> {
+        let mut bytes = dataset(&[1, 2, 3], 0);
+        bytes.truncate(bytes.len() - 3);
+
+        let metadata = Cursor::new(&bytes).read_metadata()?;
+        assert_eq!(metadata.text.total_events()?, 3);
+        assert_eq!(metadata.header.data_end - metadata.header.data_start, 2);
+        assert!(Cursor::new(&bytes).read_fcs().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_follows_nextdata() -> Result<()> {
+        let first_length = dataset(&[1, 2, 3], 0).len() as u64;
+        let mut bytes = dataset(&[1, 2, 3], first_length);
+        bytes.extend(dataset(&[4, 5], 0));
+
+        let datasets = Cursor::new(bytes).read_fcs_all()?;
+        assert_eq!(datasets.len(), 2);
+        assert_eq!(datasets[1].header.offset, first_length);
+        match &datasets[1].data {
+            Data::Int(values) => assert_eq!(values, &vec![4, 5]),
+            other => panic!("unexpected data: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_stops_on_an_overflowing_nextdata() {
+        let first_length = dataset(&[1], 0).len() as u64;
+        let mut bytes = dataset(&[1], first_length);
+        bytes.extend(dataset(&[2], u64::MAX));
+        let mut cursor = Cursor::new(bytes);
+        let mut datasets = cursor.datasets();
+
+        assert!(datasets.next().unwrap().is_ok());
+        assert!(datasets.next().unwrap().is_err());
+        assert!(datasets.next().is_none());
+    }
+
+    #[test]
+    fn it_rejects_segments_outside_the_source() {
+        let mut cursor = Cursor::new(dataset(&[1], 0));
+
+        assert!(matches!(
+            cursor.read_bytes(0, 10, u64::MAX),
+            Err(FcsError::InvalidSegment { .. })
+        ));
+        assert!(matches!(
+            cursor.read_bytes(u64::MAX, 1, 2),
+            Err(FcsError::InvalidSegment { .. })
+        ));
+        assert!(matches!(
+            cursor.read_bytes(0, 10, i64::MAX as u64),
+            Err(FcsError::TruncatedSegment { .. })
+        ));
+    }
+
+    #[test]
+    fn it_verifies_the_crc() -> Result<()> {
+        let mut bytes = dataset(&[1, 2, 3], 0);
+        let crc = crc::crc16(&bytes);
+        bytes.extend(crc::footer(crc));
+
+        let mut cursor = Cursor::new(bytes.clone());
+        let fcs = cursor.read_fcs()?;
+        assert_eq!(cursor.verify_crc(&fcs)?, Crc::Valid(crc));
+
+        let last = bytes.len() - 9;
+        bytes[last] ^= 0xff;
+        let mut cursor = Cursor::new(bytes);
+        let fcs = cursor.read_fcs()?;
+        assert!(cursor.verify_crc(&fcs).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_reports_a_crc_that_was_not_computed() -> Result<()> {
+        let mut bytes = dataset(&[1, 2, 3], 0);
+        bytes.extend(b"00000000");
+
+        let mut cursor = Cursor::new(bytes);
+        let fcs = cursor.read_fcs()?;
+        assert_eq!(cursor.verify_crc(&fcs)?, Crc::NotComputed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_ignores_what_follows_an_fcs2_data_set() -> Result<()> {
+        let mut bytes = dataset(&[1, 2, 3], 0);
+        bytes[..6].copy_from_slice(b"FCS2.0");
+        bytes.extend(b"    \0\0");
+
+        let mut cursor = Cursor::new(bytes);
+        let fcs = cursor.read_fcs()?;
+        assert_eq!(cursor.verify_crc(&fcs)?, Crc::Absent);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_round_trips_a_file() -> Result<()> {
+        let mut file =
+            File::open(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs"))?;
+        let fcs = file.read_fcs()?;
+
+        let mut bytes = vec![];
+        bytes.write_fcs(&fcs)?;
+
+        let mut cursor = Cursor::new(bytes);
+        let written = cursor.read_fcs()?;
+        assert_eq!(written.header.version, 3.1);
+        assert!(matches!(cursor.verify_crc(&written)?, Crc::Valid(_)));
+        assert_eq!(written.text.get("SPILL"), fcs.text.get("SPILL"));
+        assert_eq!(
+            written.text.get("$BEGINDATA").unwrap(),
+            &written.header.data_start.to_string()
+        );
+        match (&fcs.data, &written.data) {
+            (Data::Float(expected), Data::Float(actual)) => assert_eq!(expected, actual),
+            other => panic!("unexpected data: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_rewrites_keywords_keeping_data_verbatim() -> Result<()> {
+        let mut file =
+            File::open(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs"))?;
+        let mut fcs = file.read_fcs()?;
+        fcs.text.set("$FIL", "renamed.fcs")?;
+        fcs.text.set("$P3S", "CD45")?;
+        fcs.text.rename("SPILL", "$SPILLOVER")?;
+
+        let mut bytes = vec![];
+        file.rewrite(&fcs.header, &fcs.text, fcs.analysis.as_ref(), &mut bytes)?;
+
+        let mut cursor = Cursor::new(bytes);
+        let written = cursor.read_fcs()?;
+        assert!(matches!(cursor.verify_crc(&written)?, Crc::Valid(_)));
+        assert_eq!(written.text.get("$FIL").unwrap(), "renamed.fcs");
+        assert_eq!(written.text.get("$P3S").unwrap(), "CD45");
+        assert_eq!(written.text.get("$SPILLOVER"), fcs.text.get("$SPILLOVER"));
+        assert_eq!(written.text.get("SPILL"), None);
+        assert_eq!(
+            cursor.read_bytes(0, written.header.data_start, written.header.data_end)?,
+            file.read_bytes(0, fcs.header.data_start, fcs.header.data_end)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_compensates_fluorescence_only() -> Result<()> {
+        let mut fcs = FcsBuilder::new(DataType::Int)
+            .parameter(ParameterDefinition::new("FSC-A", 1024).bits(16))
+            .parameter(ParameterDefinition::new("FL1-A", 1024).bits(16))
+            .parameter(ParameterDefinition::new("FL2-A", 1024).bits(16))
+            // FL1 spills 10% into FL2, FL2 20% into FL1.
+            .keyword("$SPILLOVER", "2,FL1-A,FL2-A,1,0.1,0.2,1")
+            .events([[500.0, 140.0, 210.0], [600.0, 20.0, 10.0]])
+            .build()?;
+
+        fcs.compensate(None)?;
+
+        let events = fcs.events()?;
+        assert_eq!(events.column(0), Some(vec![500.0, 600.0]));
+        for (actual, expected) in events.row(0).unwrap().iter().zip([500.0, 100.0, 200.0]) {
+            assert!((actual - expected).abs() < 1e-9);
+        }
+        assert_eq!(fcs.text.data_type()?, DataType::Double);
+        assert!(matches!(
+            fcs.compensate(None),
+            Err(FcsError::AlreadyCompensated)
+        ));
+
+        let mut bytes = vec![];
+        bytes.write_fcs(&fcs)?;
+        let mut read = Fcs::from_bytes(&bytes)?;
+        assert!(read.compensation.is_none());
+        assert_eq!(
+            read.text.get(COMPENSATED).map(String::as_str),
+            Some("2,FL1-A,FL2-A,1,0.1,0.2,1")
+        );
+        assert!(matches!(
+            read.compensate(None),
+            Err(FcsError::AlreadyCompensated)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_compensates_by_parameter_name() -> Result<()> {
+        let build = || {
+            FcsBuilder::new(DataType::Float)
+                .parameter(ParameterDefinition::new("FL2-A", 1024))
+                .parameter(ParameterDefinition::new("FSC-A", 1024))
+                .parameter(ParameterDefinition::new("FL1-A", 1024))
+                .events([[210.0, 500.0, 140.0]])
+                .build()
+        };
+        let matrix = SpilloverMatrix::new(
+            vec!["FL1-A".into(), "FL2-A".into()],
+            vec![1.0, 0.1, 0.2, 1.0],
+        )?;
+
+        let mut fcs = build()?;
+        fcs.compensate(Some(&matrix))?;
+        for (actual, expected) in fcs
+            .events()?
+            .row(0)
+            .unwrap()
+            .iter()
+            .zip([200.0, 500.0, 100.0])
+        {
+            assert!((actual - expected).abs() < 1e-4);
+        }
+
+        let other = SpilloverMatrix::new(vec!["FL1-A".into(), "FL3-A".into()], matrix.values)?;
+        assert!(matches!(
+            build()?.compensate(Some(&other)),
+            Err(FcsError::UnknownParameter(name)) if name == "FL3-A"
+        ));
 
         Ok(())
     }
@@ -1,9 +1,22 @@
-use std::io::{self, Cursor, Read};
+use std::{
+    io::{Read, SeekFrom},
+    str::FromStr,
+};
 
-use crate::traits::ByteRead;
+use crate::{
+    error::{FcsError, Result},
+    keywords::RequiredKeyword,
+    text::Text,
+    traits::ByteRead,
+};
 
-#[derive(Debug)]
+/// Length of the HEADER segment.
+pub(crate) const HEADER_LENGTH: u64 = 58;
+
+#[derive(Clone, Debug)]
 pub struct Header {
+    /// Byte offset of this data set within the file.
+    pub offset: u64,
     pub version: f64,
     pub text_start: u64,
     pub text_end: u64,
@@ -14,52 +27,39 @@ pub struct Header {
 }
 
 impl Header {
-    /// Parse the FlowIo FCS file at the offset (supporting multiple data segments in a file.
-    pub(crate) fn new<R>(reader: &mut R) -> io::Result<Self>
+    /// Parse the FlowIo FCS file at the offset (supporting multiple data segments in a file).
+    pub(crate) fn new<R>(reader: &mut R, offset: u64) -> Result<Self>
     where
         R: ByteRead,
     {
-        let offset: u64 = 0;
-        let version: f64 = {
-            let mut rdr = Cursor::new(reader.read_bytes(offset, 3, 5)?);
-            let mut buf = String::new();
-            rdr.read_to_string(&mut buf)?;
-            buf.parse().expect("version to be parsed")
-        };
-
-        let text_start: u64 = {
-            let mut rdr = Cursor::new(reader.read_bytes(offset, 10, 17)?);
-            let mut buf = String::new();
-            rdr.read_to_string(&mut buf)?;
-            buf.trim().parse().expect("text_start to be parsed")
-        };
+        // Read HEADER at once, a short read being reported by the first
+        // field it cuts.
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH as usize);
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.take(HEADER_LENGTH).read_to_end(&mut bytes)?;
 
-        let text_end: u64 = {
-            let mut rdr = Cursor::new(reader.read_bytes(offset, 18, 25)?);
-            let mut buf = String::new();
-            rdr.read_to_string(&mut buf)?;
-            buf.trim().parse().expect("text_end to be parsed")
-        };
+        Header::parse(&bytes, offset)
+    }
 
-        let data_start: u64 = {
-            let mut rdr = Cursor::new(reader.read_bytes(offset, 26, 33)?);
-            let mut buf = String::new();
-            rdr.read_to_string(&mut buf)?;
-            buf.trim().parse().expect("data_start to be parsed")
-        };
+    /// Parse HEADER from its bytes, read at offset.
+    pub(crate) fn parse(bytes: &[u8], offset: u64) -> Result<Self> {
+        let magic = field_bytes(bytes, offset, 0, 2)?;
+        if magic != b"FCS" {
+            return Err(FcsError::InvalidHeader {
+                field: "magic",
+                offset,
+                value: String::from_utf8_lossy(magic).into_owned(),
+            });
+        }
 
-        let data_end: u64 = {
-            let mut rdr = Cursor::new(reader.read_bytes(offset, 34, 41)?);
-            let mut buf = String::new();
-            rdr.read_to_string(&mut buf)?;
-            buf.trim().parse().expect("data_end to be parsed")
-        };
+        let version: f64 = parse_field(bytes, offset, 3, 5, "version")?;
+        let text_start: u64 = parse_field(bytes, offset, 10, 17, "text_start")?;
+        let text_end: u64 = parse_field(bytes, offset, 18, 25, "text_end")?;
+        let data_start: u64 = parse_field(bytes, offset, 26, 33, "data_start")?;
+        let data_end: u64 = parse_field(bytes, offset, 34, 41, "data_end")?;
 
         let analysis_start: Option<u64> = {
-            let mut rdr = Cursor::new(reader.read_bytes(offset, 42, 49)?);
-            let mut buf = String::new();
-            rdr.read_to_string(&mut buf)?;
-            let analysis_start: u64 = buf.trim().parse().expect("analysis_start to be parsed");
+            let analysis_start: u64 = parse_field(bytes, offset, 42, 49, "analysis_start")?;
             if analysis_start == 0 {
                 None
             } else {
@@ -68,10 +68,7 @@ impl Header {
         };
 
         let analysis_end: Option<u64> = {
-            let mut rdr = Cursor::new(reader.read_bytes(offset, 50, 57)?);
-            let mut buf = String::new();
-            rdr.read_to_string(&mut buf)?;
-            let analysis_end: u64 = buf.trim().parse().expect("analysis_end to be parsed");
+            let analysis_end: u64 = parse_field(bytes, offset, 50, 57, "analysis_end")?;
             if analysis_end == 0 {
                 None
             } else {
@@ -80,6 +77,7 @@ impl Header {
         };
 
         Ok(Header {
+            offset,
             version,
             text_start,
             text_end,
@@ -89,4 +87,226 @@ impl Header {
             analysis_end,
         })
     }
+
+    /// Reconcile the HEADER offsets with $BEGINDATA/$ENDDATA and
+    /// $BEGINANALYSIS/$ENDANALYSIS.
+    ///
+    /// FCS 3.x writes zeros in HEADER for segments lying beyond byte
+    /// 99,999,999 and records the real offsets in TEXT only.
+    pub(crate) fn reconcile(&mut self, text: &Text) -> Result<()> {
+        let data = reconcile_segment(
+            text,
+            (self.data_start, self.data_end),
+            RequiredKeyword::BeginData,
+            RequiredKeyword::EndData,
+        )?;
+        (self.data_start, self.data_end) = data;
+
+        let analysis = reconcile_segment(
+            text,
+            (
+                self.analysis_start.unwrap_or(0),
+                self.analysis_end.unwrap_or(0),
+            ),
+            RequiredKeyword::BeginAnalysis,
+            RequiredKeyword::EndAnalysis,
+        )?;
+        if analysis != (0, 0) {
+            self.analysis_start = Some(analysis.0);
+            self.analysis_end = Some(analysis.1);
+        }
+
+        Ok(())
+    }
+}
+
+/// Pick the offsets of a segment from HEADER, falling back to the TEXT
+/// keywords when HEADER holds zeros.
+fn reconcile_segment(
+    text: &Text,
+    header: (u64, u64),
+    begin: RequiredKeyword,
+    end: RequiredKeyword,
+) -> Result<(u64, u64)> {
+    let begin_text: Option<u64> = text.parse_optional(&begin)?;
+    let end_text: Option<u64> = text.parse_optional(&end)?;
+
+    if header == (0, 0) {
+        return match (begin_text, end_text) {
+            (Some(start), Some(end)) if end < start => Err(FcsError::InvalidSegment { start, end }),
+            (Some(begin), Some(end)) => Ok((begin, end)),
+            (None, Some(end_text)) if end_text != 0 => {
+                Err(FcsError::MissingKeyword(begin.to_string()))
+            }
+            (Some(begin_text), None) if begin_text != 0 => {
+                Err(FcsError::MissingKeyword(end.to_string()))
+            }
+            _ => Ok(header),
+        };
+    }
+
+    for (keyword, header, text) in [(begin, header.0, begin_text), (end, header.1, end_text)] {
+        match text {
+            Some(text) if text != 0 && text != header => {
+                return Err(FcsError::OffsetMismatch {
+                    keyword: keyword.to_string(),
+                    header,
+                    text,
+                })
+            }
+            _ => {}
+        }
+    }
+
+    Ok(header)
+}
+
+/// Bytes of a HEADER field, from start to stop inclusive.
+fn field_bytes(bytes: &[u8], offset: u64, start: usize, stop: usize) -> Result<&[u8]> {
+    bytes
+        .get(start..=stop)
+        .ok_or_else(|| FcsError::TruncatedSegment {
+            offset: offset + start as u64,
+            expected: (stop - start + 1) as u64,
+            found: bytes.len().saturating_sub(start) as u64,
+        })
+}
+
+/// Read the ASCII field between start and stop inclusive and parse it.
+fn parse_field<T>(
+    bytes: &[u8],
+    offset: u64,
+    start: usize,
+    stop: usize,
+    field: &'static str,
+) -> Result<T>
+where
+    T: FromStr,
+{
+    let value = String::from_utf8_lossy(field_bytes(bytes, offset, start, stop)?);
+
+    value.trim().parse().map_err(|_| FcsError::InvalidHeader {
+        field,
+        offset: offset + start as u64,
+        value: value.into_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{error::FcsError, text::Text, traits::FcsRead};
+
+    use super::Header;
+
+    fn header(data_start: u64, data_end: u64) -> Header {
+        Header {
+            offset: 0,
+            version: 3.0,
+            text_start: 58,
+            text_end: 1000,
+            data_start,
+            data_end,
+            analysis_start: None,
+            analysis_end: None,
+        }
+    }
+
+    #[test]
+    fn it_rejects_a_malformed_offset() {
+        let mut bytes = b"FCS3.0          58     abc       0       0       0       0".to_vec();
+        bytes.resize(256, b' ');
+
+        match Header::new(&mut Cursor::new(bytes), 0) {
+            Err(FcsError::InvalidHeader { field, offset, .. }) => {
+                assert_eq!(field, "text_end");
+                assert_eq!(offset, 18);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_rejects_a_truncated_header() {
+        match Header::new(&mut Cursor::new(b"FCS3.0    ".to_vec()), 0) {
+            Err(FcsError::TruncatedSegment { offset, .. }) => assert_eq!(offset, 10),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_takes_large_offsets_from_text() {
+        let text = Text::new(
+            b"\\$BEGINDATA\\120000000\\$ENDDATA\\240000000\\$BEGINANALYSIS\\0\\$ENDANALYSIS\\0\\",
+        )
+        .unwrap();
+        let mut header = header(0, 0);
+
+        header.reconcile(&text).unwrap();
+        assert_eq!(header.data_start, 120000000);
+        assert_eq!(header.data_end, 240000000);
+        assert_eq!(header.analysis_start, None);
+    }
+
+    #[test]
+    fn it_reports_mismatching_offsets() {
+        let text = Text::new(b"\\$BEGINDATA\\1001\\$ENDDATA\\2000\\").unwrap();
+        let mut header = header(1001, 1999);
+
+        match header.reconcile(&text) {
+            Err(FcsError::OffsetMismatch {
+                keyword,
+                header,
+                text,
+            }) => {
+                assert_eq!(keyword, "$ENDDATA");
+                assert_eq!(header, 1999);
+                assert_eq!(text, 2000);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_rejects_segments_text_declares_backwards() {
+        let text = Text::new(b"\\$BEGINDATA\\2000\\$ENDDATA\\1000\\").unwrap();
+
+        assert!(matches!(
+            header(0, 0).reconcile(&text),
+            Err(FcsError::InvalidSegment {
+                start: 2000,
+                end: 1000
+            })
+        ));
+    }
+
+    #[test]
+    fn it_rejects_a_segment_text_declares_past_the_end() {
+        let text = b"\\$BEGINANALYSIS\\0\\$ENDANALYSIS\\0\\$BEGINSTEXT\\0\\$ENDSTEXT\\0\\\
+            $BEGINDATA\\256\\$ENDDATA\\9223372036854775807\\$BYTEORD\\1,2\\$DATATYPE\\I\\\
+            $MODE\\L\\$NEXTDATA\\0\\$PAR\\1\\$TOT\\1\\$P1B\\16\\$P1E\\0,0\\$P1N\\A\\$P1R\\1024\\";
+        let mut bytes = format!(
+            "FCS3.0    {:>8}{:>8}{:>8}{:>8}{:>8}{:>8}",
+            58,
+            57 + text.len(),
+            0,
+            0,
+            0,
+            0
+        )
+        .into_bytes();
+        bytes.extend_from_slice(text);
+        bytes.resize(4096, 0);
+
+        match Cursor::new(bytes).read_fcs() {
+            Err(FcsError::TruncatedSegment {
+                offset, expected, ..
+            }) => {
+                assert_eq!(offset, 256);
+                assert_eq!(expected, 9223372036854775807 - 255);
+            }
+            other => panic!("unexpected result: {:?}", other.map(|fcs| fcs.header)),
+        }
+    }
 }
@@ -0,0 +1,239 @@
+use std::mem;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::{
+    data::{encode_value, invalid_bits, Byteord, DataType, Mode, Value},
+    error::{FcsError, Result},
+    text::Parameter,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum Histogram {
+    /// $MODE=C: a count for every combination of channels, the first
+    /// parameter varying fastest. `shape` holds each parameter's $PnR.
+    Correlated { shape: Vec<u64>, counts: Vec<f64> },
+    /// $MODE=U: one histogram of $PnR channels per parameter.
+    Uncorrelated(Vec<Vec<f64>>),
+}
+
+impl Histogram {
+    /// Decode a DATA segment stored in one of the histogram modes.
+    pub fn new(
+        bytes: &[u8],
+        mode: Mode,
+        data_type: DataType,
+        byteord: Byteord,
+        parameters: &[Parameter],
+    ) -> Result<Self> {
+        let mut reader = CountReader {
+            bytes,
+            offset: 0,
+            data_type,
+            byteord,
+        };
+
+        match mode {
+            Mode::Correlated => {
+                let shape: Vec<u64> = parameters.iter().map(|p| p.range.end).collect();
+                let cells = shape
+                    .iter()
+                    .try_fold(1u64, |cells, range| cells.checked_mul(*range))
+                    .unwrap_or(u64::MAX);
+                let counts = match parameters.first() {
+                    Some(parameter) => reader.read(parameter, cells)?,
+                    None => vec![],
+                };
+
+                Ok(Histogram::Correlated { shape, counts })
+            }
+            Mode::Uncorrelated => Ok(Histogram::Uncorrelated(
+                parameters
+                    .iter()
+                    .map(|p| reader.read(p, p.range.end))
+                    .collect::<Result<_>>()?,
+            )),
+            Mode::List => Err(FcsError::InvalidKeyword {
+                keyword: "$MODE".into(),
+                value: "L".into(),
+            }),
+        }
+    }
+}
+
+impl Histogram {
+    /// Total number of counts.
+    pub fn len(&self) -> usize {
+        match self {
+            Histogram::Correlated { counts, .. } => counts.len(),
+            Histogram::Uncorrelated(histograms) => histograms.iter().map(Vec::len).sum(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Encode the counts as the bytes of a DATA segment.
+    pub fn to_bytes(
+        &self,
+        data_type: DataType,
+        byteord: &Byteord,
+        parameters: &[Parameter],
+    ) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        match self {
+            Histogram::Correlated { counts, .. } => {
+                if let Some(parameter) = parameters.first() {
+                    for count in counts {
+                        encode_value(
+                            &mut bytes,
+                            Value::Double(*count),
+                            data_type,
+                            byteord,
+                            parameter,
+                        )?;
+                    }
+                }
+            }
+            Histogram::Uncorrelated(histograms) => {
+                for (counts, parameter) in histograms.iter().zip(parameters) {
+                    for count in counts {
+                        encode_value(
+                            &mut bytes,
+                            Value::Double(*count),
+                            data_type,
+                            byteord,
+                            parameter,
+                        )?;
+                    }
+                }
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Reads consecutive histogram counts out of the DATA segment.
+struct CountReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    data_type: DataType,
+    byteord: Byteord,
+}
+
+impl CountReader<'_> {
+    /// Read `count` values encoded as described by the parameter.
+    fn read(&mut self, parameter: &Parameter, count: u64) -> Result<Vec<f64>> {
+        let width = match (self.data_type, parameter.bits) {
+            (DataType::Float, _) => mem::size_of::<f32>(),
+            (DataType::Double, _) => mem::size_of::<f64>(),
+            (DataType::Int, Some(bits)) if bits.is_multiple_of(8) && (8..=64).contains(&bits) => {
+                bits as usize / 8
+            }
+            (DataType::Ascii, Some(bits)) if bits > 0 => bits as usize,
+            _ => return Err(invalid_bits(parameter)),
+        };
+
+        let length = (width as u64).saturating_mul(count);
+        let available = (self.bytes.len() - self.offset) as u64;
+        if available < length {
+            return Err(FcsError::TruncatedSegment {
+                offset: self.offset as u64,
+                expected: length,
+                found: available,
+            });
+        }
+
+        let length = length as usize;
+        let mut counts = Vec::with_capacity(count as usize);
+        for chunk in self.bytes[self.offset..self.offset + length].chunks_exact(width) {
+            counts.push(match (self.data_type, &self.byteord) {
+                (DataType::Ascii, _) => {
+                    let value = String::from_utf8_lossy(chunk);
+                    value.trim().parse().map_err(|_| FcsError::InvalidData {
+                        offset: self.offset as u64,
+                        value: value.into_owned(),
+                    })?
+                }
+                (DataType::Int, Byteord::BigEndian(_)) => BigEndian::read_uint(chunk, width) as f64,
+                (DataType::Int, Byteord::LittleEndian(_)) => {
+                    LittleEndian::read_uint(chunk, width) as f64
+                }
+                (DataType::Float, Byteord::BigEndian(_)) => BigEndian::read_f32(chunk) as f64,
+                (DataType::Float, Byteord::LittleEndian(_)) => LittleEndian::read_f32(chunk) as f64,
+                (DataType::Double, Byteord::BigEndian(_)) => BigEndian::read_f64(chunk),
+                (DataType::Double, Byteord::LittleEndian(_)) => LittleEndian::read_f64(chunk),
+            });
+            self.offset += width;
+        }
+
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::{Byteord, DataType, Mode},
+        text::Text,
+    };
+
+    use super::Histogram;
+
+    #[test]
+    fn it_decodes_uncorrelated_histograms() {
+        let text = Text::new(
+            b"\\$PAR\\2\\$P1B\\16\\$P1N\\FSC-H\\$P1R\\2\\$P2B\\16\\$P2N\\SSC-H\\$P2R\\3\\",
+        )
+        .unwrap();
+        let bytes: Vec<u8> = [5u16, 6, 7, 8, 9]
+            .iter()
+            .flat_map(|c| c.to_be_bytes())
+            .collect();
+
+        let histogram = Histogram::new(
+            &bytes,
+            Mode::Uncorrelated,
+            DataType::Int,
+            Byteord::BigEndian("2,1".into()),
+            &text.parameters().unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            histogram,
+            Histogram::Uncorrelated(vec![vec![5.0, 6.0], vec![7.0, 8.0, 9.0]])
+        );
+    }
+
+    #[test]
+    fn it_decodes_a_correlated_histogram() {
+        let text = Text::new(
+            b"\\$PAR\\2\\$P1B\\32\\$P1N\\FSC-H\\$P1R\\2\\$P2B\\32\\$P2N\\SSC-H\\$P2R\\2\\",
+        )
+        .unwrap();
+        let bytes: Vec<u8> = [1.0f32, 2.0, 3.0, 4.0]
+            .iter()
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+
+        let histogram = Histogram::new(
+            &bytes,
+            Mode::Correlated,
+            DataType::Float,
+            Byteord::LittleEndian("1,2,3,4".into()),
+            &text.parameters().unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            histogram,
+            Histogram::Correlated {
+                shape: vec![2, 2],
+                counts: vec![1.0, 2.0, 3.0, 4.0],
+            }
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 pub enum RequiredKeyword {
     BeginAnalysis,
     BeginData,
@@ -15,7 +17,10 @@ pub enum RequiredKeyword {
 
 pub enum OptionalKeyword {
     Abrt,
+    BeginDateTime,
     Btim,
+    CarrierId,
+    CarrierType,
     Cells,
     Com,
     Csmode,
@@ -23,13 +28,16 @@ pub enum OptionalKeyword {
     Cyt,
     Cytsn,
     Date,
+    EndDateTime,
     Etim,
     Exp,
     Fil,
+    Flowrate,
     Gate,
     Inst,
     LastModified,
     LastModifier,
+    LocationId,
     Lost,
     Op,
     Originality,
@@ -42,6 +50,8 @@ pub enum OptionalKeyword {
     Sys,
     Timestep,
     Tr,
+    UnstainedCenters,
+    UnstainedInfo,
     Vol,
     WellId,
 }
@@ -57,7 +67,7 @@ impl TryFrom<String> for RequiredKeyword {
             "$BYTEORD" => Ok(RequiredKeyword::Byteord),
             "$DATATYPE" => Ok(RequiredKeyword::DataType),
             "$ENDANALYSIS" => Ok(RequiredKeyword::EndAnalysis),
-            "$ENDATA" => Ok(RequiredKeyword::EndData),
+            "$ENDDATA" => Ok(RequiredKeyword::EndData),
             "$ENDSTEXT" => Ok(RequiredKeyword::EndsText),
             "$MODE" => Ok(RequiredKeyword::Mode),
             "$NEXTDATA" => Ok(RequiredKeyword::NextData),
@@ -68,22 +78,22 @@ impl TryFrom<String> for RequiredKeyword {
     }
 }
 
-impl ToString for RequiredKeyword {
-    fn to_string(&self) -> String {
-        match self {
-            RequiredKeyword::BeginAnalysis => "$BEGINANALYSIS".into(),
-            RequiredKeyword::BeginData => "$BEGINDATA".into(),
-            RequiredKeyword::BeginsText => "$BEGINSTEXT".into(),
-            RequiredKeyword::Byteord => "$BYTEORD".into(),
-            RequiredKeyword::DataType => "$DATATYPE".into(),
-            RequiredKeyword::EndAnalysis => "$ENDANALYSIS".into(),
-            RequiredKeyword::EndData => "$ENDATA".into(),
-            RequiredKeyword::EndsText => "$ENDSTEXT".into(),
-            RequiredKeyword::Mode => "$MODE".into(),
-            RequiredKeyword::NextData => "$NEXTDATA".into(),
-            RequiredKeyword::Par => "$PAR".into(),
-            RequiredKeyword::Tot => "$TOT".into(),
-        }
+impl fmt::Display for RequiredKeyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RequiredKeyword::BeginAnalysis => "$BEGINANALYSIS",
+            RequiredKeyword::BeginData => "$BEGINDATA",
+            RequiredKeyword::BeginsText => "$BEGINSTEXT",
+            RequiredKeyword::Byteord => "$BYTEORD",
+            RequiredKeyword::DataType => "$DATATYPE",
+            RequiredKeyword::EndAnalysis => "$ENDANALYSIS",
+            RequiredKeyword::EndData => "$ENDDATA",
+            RequiredKeyword::EndsText => "$ENDSTEXT",
+            RequiredKeyword::Mode => "$MODE",
+            RequiredKeyword::NextData => "$NEXTDATA",
+            RequiredKeyword::Par => "$PAR",
+            RequiredKeyword::Tot => "$TOT",
+        })
     }
 }
 
@@ -93,7 +103,10 @@ impl TryFrom<String> for OptionalKeyword {
     fn try_from(value: String) -> Result<Self, Self::Error> {
         match value.as_ref() {
             "$ABRT" => Ok(OptionalKeyword::Abrt),
+            "$BEGINDATETIME" => Ok(OptionalKeyword::BeginDateTime),
             "$BTIM" => Ok(OptionalKeyword::Btim),
+            "$CARRIERID" => Ok(OptionalKeyword::CarrierId),
+            "$CARRIERTYPE" => Ok(OptionalKeyword::CarrierType),
             "$CELLS" => Ok(OptionalKeyword::Cells),
             "$COM" => Ok(OptionalKeyword::Com),
             "$CSMODE" => Ok(OptionalKeyword::Csmode),
@@ -101,13 +114,16 @@ impl TryFrom<String> for OptionalKeyword {
             "$CYT" => Ok(OptionalKeyword::Cyt),
             "$CYTSN" => Ok(OptionalKeyword::Cytsn),
             "$DATE" => Ok(OptionalKeyword::Date),
+            "$ENDDATETIME" => Ok(OptionalKeyword::EndDateTime),
             "$ETIM" => Ok(OptionalKeyword::Etim),
             "$EXP" => Ok(OptionalKeyword::Exp),
             "$FIL" => Ok(OptionalKeyword::Fil),
+            "$FLOWRATE" => Ok(OptionalKeyword::Flowrate),
             "$GATE" => Ok(OptionalKeyword::Gate),
-            "$ISNT" => Ok(OptionalKeyword::Inst),
+            "$INST" => Ok(OptionalKeyword::Inst),
             "$LAST_MODIFIED" => Ok(OptionalKeyword::LastModified),
             "$LAST_MODIFIER" => Ok(OptionalKeyword::LastModifier),
+            "$LOCATIONID" => Ok(OptionalKeyword::LocationId),
             "$LOST" => Ok(OptionalKeyword::Lost),
             "$OP" => Ok(OptionalKeyword::Op),
             "$ORIGINALITY" => Ok(OptionalKeyword::Originality),
@@ -120,6 +136,8 @@ impl TryFrom<String> for OptionalKeyword {
             "$SYS" => Ok(OptionalKeyword::Sys),
             "$TIMESTEP" => Ok(OptionalKeyword::Timestep),
             "$TR" => Ok(OptionalKeyword::Tr),
+            "$UNSTAINEDCENTERS" => Ok(OptionalKeyword::UnstainedCenters),
+            "$UNSTAINEDINFO" => Ok(OptionalKeyword::UnstainedInfo),
             "$VOL" => Ok(OptionalKeyword::Vol),
             "$WELLID" => Ok(OptionalKeyword::WellId),
             _ => Err("oh no!"),
@@ -127,39 +145,174 @@ impl TryFrom<String> for OptionalKeyword {
     }
 }
 
-impl ToString for OptionalKeyword {
-    fn to_string(&self) -> String {
-        match self {
-            OptionalKeyword::Abrt => "$ABRT".into(),
-            OptionalKeyword::Btim => "$BTIM".into(),
-            OptionalKeyword::Cells => "$CELLS".into(),
-            OptionalKeyword::Com => "$COM".into(),
-            OptionalKeyword::Csmode => "$CSMODE".into(),
-            OptionalKeyword::Csvbits => "$CSVBITS".into(),
-            OptionalKeyword::Cyt => "$CYT".into(),
-            OptionalKeyword::Cytsn => "$CYTSN".into(),
-            OptionalKeyword::Date => "$DATE".into(),
-            OptionalKeyword::Etim => "$ETIM".into(),
-            OptionalKeyword::Exp => "$EXP".into(),
-            OptionalKeyword::Fil => "$FIL".into(),
-            OptionalKeyword::Gate => "$GATE".into(),
-            OptionalKeyword::Inst => "$ISNT".into(),
-            OptionalKeyword::LastModified => "$LAST_MODIFIED".into(),
-            OptionalKeyword::LastModifier => "$LAST_MODIFIER".into(),
-            OptionalKeyword::Lost => "$LOST".into(),
-            OptionalKeyword::Op => "$OP".into(),
-            OptionalKeyword::Originality => "$ORIGINALITY".into(),
-            OptionalKeyword::PlateId => "$PLATEID".into(),
-            OptionalKeyword::PlateName => "$PLATENAME".into(),
-            OptionalKeyword::Proj => "$PROJ".into(),
-            OptionalKeyword::Smno => "$SMNO".into(),
-            OptionalKeyword::Spillover => "$SPILLOVER".into(),
-            OptionalKeyword::Src => "$SRC".into(),
-            OptionalKeyword::Sys => "$SYS".into(),
-            OptionalKeyword::Timestep => "$TIMESTEP".into(),
-            OptionalKeyword::Tr => "$TR".into(),
-            OptionalKeyword::Vol => "$VOL".into(),
-            OptionalKeyword::WellId => "$WELLID".into(),
-        }
+impl fmt::Display for OptionalKeyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OptionalKeyword::Abrt => "$ABRT",
+            OptionalKeyword::BeginDateTime => "$BEGINDATETIME",
+            OptionalKeyword::Btim => "$BTIM",
+            OptionalKeyword::CarrierId => "$CARRIERID",
+            OptionalKeyword::CarrierType => "$CARRIERTYPE",
+            OptionalKeyword::Cells => "$CELLS",
+            OptionalKeyword::Com => "$COM",
+            OptionalKeyword::Csmode => "$CSMODE",
+            OptionalKeyword::Csvbits => "$CSVBITS",
+            OptionalKeyword::Cyt => "$CYT",
+            OptionalKeyword::Cytsn => "$CYTSN",
+            OptionalKeyword::Date => "$DATE",
+            OptionalKeyword::EndDateTime => "$ENDDATETIME",
+            OptionalKeyword::Etim => "$ETIM",
+            OptionalKeyword::Exp => "$EXP",
+            OptionalKeyword::Fil => "$FIL",
+            OptionalKeyword::Flowrate => "$FLOWRATE",
+            OptionalKeyword::Gate => "$GATE",
+            OptionalKeyword::Inst => "$INST",
+            OptionalKeyword::LastModified => "$LAST_MODIFIED",
+            OptionalKeyword::LastModifier => "$LAST_MODIFIER",
+            OptionalKeyword::LocationId => "$LOCATIONID",
+            OptionalKeyword::Lost => "$LOST",
+            OptionalKeyword::Op => "$OP",
+            OptionalKeyword::Originality => "$ORIGINALITY",
+            OptionalKeyword::PlateId => "$PLATEID",
+            OptionalKeyword::PlateName => "$PLATENAME",
+            OptionalKeyword::Proj => "$PROJ",
+            OptionalKeyword::Smno => "$SMNO",
+            OptionalKeyword::Spillover => "$SPILLOVER",
+            OptionalKeyword::Src => "$SRC",
+            OptionalKeyword::Sys => "$SYS",
+            OptionalKeyword::Timestep => "$TIMESTEP",
+            OptionalKeyword::Tr => "$TR",
+            OptionalKeyword::UnstainedCenters => "$UNSTAINEDCENTERS",
+            OptionalKeyword::UnstainedInfo => "$UNSTAINEDINFO",
+            OptionalKeyword::Vol => "$VOL",
+            OptionalKeyword::WellId => "$WELLID",
+        })
+    }
+}
+
+/// Keywords describing the n-th parameter, such as $PnN or $PnDATATYPE.
+pub enum ParameterKeyword {
+    Amplification,
+    Analyte,
+    Bits,
+    Calibration,
+    DataType,
+    Detector,
+    DetectorType,
+    DetectorVoltage,
+    Display,
+    EmittedLight,
+    ExcitationPower,
+    ExcitationWavelength,
+    Feature,
+    Filter,
+    Gain,
+    LongName,
+    Range,
+    ShortName,
+    Tag,
+    Type,
+}
+
+impl ParameterKeyword {
+    /// Keyword for the parameter at index, starting from 1.
+    pub fn of(&self, index: u32) -> String {
+        let suffix = match self {
+            ParameterKeyword::Amplification => "E",
+            ParameterKeyword::Analyte => "ANALYTE",
+            ParameterKeyword::Bits => "B",
+            ParameterKeyword::Calibration => "CALIBRATION",
+            ParameterKeyword::DataType => "DATATYPE",
+            ParameterKeyword::Detector => "DET",
+            ParameterKeyword::DetectorType => "T",
+            ParameterKeyword::DetectorVoltage => "V",
+            ParameterKeyword::Display => "D",
+            ParameterKeyword::EmittedLight => "P",
+            ParameterKeyword::ExcitationPower => "O",
+            ParameterKeyword::ExcitationWavelength => "L",
+            ParameterKeyword::Feature => "FEATURE",
+            ParameterKeyword::Filter => "F",
+            ParameterKeyword::Gain => "G",
+            ParameterKeyword::LongName => "S",
+            ParameterKeyword::Range => "R",
+            ParameterKeyword::ShortName => "N",
+            ParameterKeyword::Tag => "TAG",
+            ParameterKeyword::Type => "TYPE",
+        };
+
+        format!("$P{}{}", index, suffix)
     }
 }
+
+/// Suffixes the standard defines for $Pn keywords.
+const PARAMETER_SUFFIXES: &[&str] = &[
+    "E",
+    "ANALYTE",
+    "B",
+    "CALIBRATION",
+    "DATATYPE",
+    "DET",
+    "T",
+    "V",
+    "D",
+    "P",
+    "O",
+    "L",
+    "FEATURE",
+    "F",
+    "G",
+    "S",
+    "R",
+    "N",
+    "TAG",
+    "TYPE",
+];
+
+/// Whether the keyword is one the standard defines. The `$` prefix is
+/// reserved to those, so any other `$` keyword is invalid.
+pub fn is_standard(keyword: &str) -> bool {
+    let keyword = keyword.to_ascii_uppercase();
+    if RequiredKeyword::try_from(keyword.clone()).is_ok()
+        || OptionalKeyword::try_from(keyword.clone()).is_ok()
+        || keyword == "$COMP"
+        || keyword == "$GATING"
+    {
+        return true;
+    }
+
+    let indexed = |prefix: &str, suffixes: &[&str]| {
+        keyword.strip_prefix(prefix).is_some_and(|rest| {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            digits > 0 && suffixes.contains(&&rest[digits..])
+        })
+    };
+
+    indexed("$P", PARAMETER_SUFFIXES)
+        || indexed("$G", &["E", "F", "N", "P", "R", "S", "T", "V"])
+        || indexed("$R", &["I", "W"])
+        || indexed("$PKN", &[""])
+        || indexed("$PK", &[""])
+        || indexed("$CSV", &["FLAG"])
+        || keyword
+            .strip_prefix("$DFC")
+            .and_then(|rest| rest.split_once("TO"))
+            .is_some_and(|(i, j)| {
+                [i, j]
+                    .iter()
+                    .all(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+            })
+}
+
+/// Whether the keyword describes how DATA is laid out, so that changing
+/// it would reinterpret the bytes of the segment.
+pub fn describes_data(keyword: &str) -> bool {
+    let keyword = keyword.to_ascii_uppercase();
+    if RequiredKeyword::try_from(keyword.clone()).is_ok() {
+        return true;
+    }
+
+    keyword.strip_prefix("$P").is_some_and(|rest| {
+        let suffix = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+        suffix.len() < rest.len() && ["B", "R", "DATATYPE"].contains(&suffix)
+    })
+}
@@ -1,8 +1,22 @@
 pub mod analysis;
+pub mod anonymize;
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod builder;
+pub mod compensation;
+pub mod crc;
 pub mod data;
+pub mod date;
+pub mod error;
+pub mod events;
 pub mod fcs;
 pub mod header;
+pub mod histogram;
 pub mod keywords;
+#[cfg(feature = "mmap")]
+pub mod mmap;
 pub mod prelude;
+pub mod stream;
 pub mod text;
 pub mod traits;
+pub mod writer;
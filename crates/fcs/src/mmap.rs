@@ -0,0 +1,117 @@
+use std::{fs::File, io::Cursor, path::Path};
+
+use memmap2::Mmap;
+
+use crate::{
+    analysis::Analysis,
+    data::Column,
+    error::Result,
+    header::Header,
+    text::{Parameter, Text},
+    traits::{read_text, segment_range},
+};
+
+/// A data set read from a memory-mapped file, whose DATA segment is never
+/// copied: columns are views decoding values as they are accessed.
+pub struct MmapFcs {
+    mmap: Mmap,
+    pub header: Header,
+    pub text: Text,
+    pub analysis: Option<Analysis>,
+    parameters: Vec<Parameter>,
+}
+
+impl MmapFcs {
+    /// Map the file and read the first data set.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::open(path)?;
+        // The mapping is only sound while no one truncates the file, as with
+        // any other memory-mapped reader.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let (header, text) = read_text(&mut Cursor::new(&mmap[..]), 0)?;
+        let analysis = match (header.analysis_start, header.analysis_end) {
+            (Some(start), Some(end)) => Some(Analysis::new(segment(&mmap, start, end)?)?),
+            _ => None,
+        };
+        let parameters = text.parameters()?;
+
+        Ok(MmapFcs {
+            mmap,
+            header,
+            text,
+            analysis,
+            parameters,
+        })
+    }
+
+    /// Bytes of the DATA segment, as stored in the file.
+    pub fn data(&self) -> Result<&[u8]> {
+        match (self.header.data_start, self.header.data_end) {
+            (0, 0) => Ok(&[]),
+            (start, end) => segment(&self.mmap, start, end),
+        }
+    }
+
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// View over the values of the parameter at index, starting from 0.
+    pub fn column(&self, index: usize) -> Result<Column<'_>> {
+        self.text.require_list_mode()?;
+        Column::new(
+            self.data()?,
+            self.text.data_type()?,
+            &self.text.byteord()?,
+            &self.parameters,
+            index,
+        )
+    }
+
+    /// Views over every parameter, in order.
+    pub fn columns(&self) -> Result<Vec<Column<'_>>> {
+        (0..self.parameters.len())
+            .map(|index| self.column(index))
+            .collect()
+    }
+}
+
+/// Bytes from start to end inclusive.
+fn segment(bytes: &[u8], start: u64, end: u64) -> Result<&[u8]> {
+    let (start, length) = segment_range(0, start, end, bytes.len() as u64)?;
+
+    Ok(&bytes[start as usize..(start + length) as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, path::PathBuf};
+
+    use crate::{data::Data, error::Result, traits::FcsRead};
+
+    use super::MmapFcs;
+
+    #[test]
+    fn it_views_columns_over_the_mapped_file() -> Result<()> {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs");
+        let mapped = MmapFcs::open(&path)?;
+        let fcs = File::open(&path)?.read_fcs()?;
+
+        let values = match fcs.data {
+            Data::Float(values) => values,
+            other => panic!("unexpected data: {:?}", other),
+        };
+        let column = mapped.column(3)?;
+        assert_eq!(column.len(), 65016);
+        for (event, value) in column.iter().enumerate() {
+            assert_eq!(value.as_f64(), values[event * 16 + 3] as f64);
+        }
+        assert!(mapped.column(16).is_err());
+
+        Ok(())
+    }
+}
@@ -1,10 +1,13 @@
-use std::fs::File;
+use std::io::{Read, Seek, Write};
 
 use crate::traits::ByteRead;
-pub use crate::traits::{FcsRead, FcsWrite};
+pub use crate::{
+    error::FcsError,
+    traits::{FcsRead, FcsWrite},
+};
 
-impl ByteRead for File {}
+impl<T> ByteRead for T where T: Read + Seek {}
 
-impl FcsRead for File {}
+impl<T> FcsRead for T where T: Read + Seek {}
 
-impl FcsWrite for File {}
+impl<T> FcsWrite for T where T: Write {}
@@ -0,0 +1,228 @@
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+use crate::{
+    data::{Byteord, Field, Value},
+    error::{FcsError, Result},
+    header::Header,
+    text::Text,
+    traits::{read_text, segment_range, FcsRead},
+};
+
+/// Capacity of the buffer events are read through.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Reads list mode events from the DATA segment one at a time, holding no
+/// more than a buffer of the segment in memory. Values are decoded as
+/// `Field::new` describes.
+pub struct EventStream<'a, R> {
+    reader: BufReader<&'a mut R>,
+    pub header: Header,
+    pub text: Text,
+    byteord: Byteord,
+    fields: Vec<Field>,
+    event: Vec<u8>,
+    /// Offset of the next event in the source.
+    position: u64,
+    remaining: u64,
+}
+
+impl<'a, R> EventStream<'a, R>
+where
+    R: FcsRead,
+{
+    /// Prepare to stream the data set whose HEADER starts at offset.
+    pub(crate) fn new(reader: &'a mut R, offset: u64) -> Result<Self> {
+        let (header, text) = read_text(reader, offset)?;
+        text.require_list_mode()?;
+
+        let data_type = text.data_type()?;
+        let fields = text
+            .parameters()?
+            .iter()
+            .map(|p| Field::new(p, data_type))
+            .collect::<Result<Vec<_>>>()?;
+        let width: usize = fields.iter().map(|field| field.width).sum();
+
+        let (start, length) = match (header.data_start, header.data_end) {
+            (0, 0) => (0, 0),
+            (start, end) => {
+                let source = reader.seek(SeekFrom::End(0))?;
+                segment_range(offset, start, end, source)?
+            }
+        };
+        let remainder = match width {
+            0 => 0,
+            width => length % width as u64,
+        };
+        if remainder != 0 {
+            return Err(FcsError::TruncatedSegment {
+                offset: start + length - remainder,
+                expected: width as u64,
+                found: remainder,
+            });
+        }
+
+        let mut reader = BufReader::with_capacity(BUFFER_SIZE, reader);
+        reader.seek(SeekFrom::Start(start))?;
+
+        Ok(EventStream {
+            reader,
+            byteord: text.byteord()?,
+            header,
+            text,
+            fields,
+            event: vec![0; width],
+            position: start,
+            remaining: match width {
+                0 => 0,
+                width => length / width as u64,
+            },
+        })
+    }
+
+    /// Number of events not read yet.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Read up to `events` events, at least one, in event-major order, or
+    /// None once every event has been read.
+    pub fn next_chunk(&mut self, events: usize) -> Option<Result<Vec<Value>>> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let events = (events.max(1) as u64).min(self.remaining) as usize;
+        let mut values = Vec::with_capacity(events * self.fields.len());
+        for _ in 0..events {
+            if let Err(err) = self.read_event(&mut values) {
+                // Stop at the first error rather than reading misaligned events.
+                self.remaining = 0;
+                return Some(Err(err));
+            }
+        }
+
+        Some(Ok(values))
+    }
+
+    fn read_event(&mut self, values: &mut Vec<Value>) -> Result<()> {
+        let mut found = 0;
+        while found < self.event.len() {
+            match self.reader.read(&mut self.event[found..])? {
+                0 => {
+                    return Err(FcsError::TruncatedSegment {
+                        offset: self.position,
+                        expected: self.event.len() as u64,
+                        found: found as u64,
+                    })
+                }
+                read => found += read,
+            }
+        }
+        self.position += found as u64;
+        self.remaining -= 1;
+
+        let mut rest = &self.event[..];
+        for field in &self.fields {
+            let (chunk, tail) = rest.split_at(field.width);
+            rest = tail;
+            values.push(field.read(chunk, &self.byteord));
+        }
+
+        Ok(())
+    }
+}
+
+impl<R> Iterator for EventStream<'_, R>
+where
+    R: FcsRead,
+{
+    type Item = Result<Vec<Value>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Cursor, path::PathBuf};
+
+    use crate::{
+        data::Data,
+        error::{FcsError, Result},
+        traits::FcsRead,
+    };
+
+    /// A 4 KB source whose TEXT declares DATA from 256 to `end`.
+    fn declaring_data_to(end: u64) -> Cursor<Vec<u8>> {
+        let text = format!(
+            "\\$BEGINANALYSIS\\0\\$ENDANALYSIS\\0\\$BEGINSTEXT\\0\\$ENDSTEXT\\0\\\
+             $BEGINDATA\\256\\$ENDDATA\\{}\\$BYTEORD\\1,2\\$DATATYPE\\I\\$MODE\\L\\\
+             $NEXTDATA\\0\\$PAR\\1\\$TOT\\1\\$P1B\\16\\$P1E\\0,0\\$P1N\\A\\$P1R\\1024\\",
+            end
+        );
+        let mut bytes = format!(
+            "FCS3.0    {:>8}{:>8}{:>8}{:>8}{:>8}{:>8}",
+            58,
+            57 + text.len(),
+            0,
+            0,
+            0,
+            0
+        )
+        .into_bytes();
+        bytes.extend_from_slice(text.as_bytes());
+        bytes.resize(4096, 0);
+
+        Cursor::new(bytes)
+    }
+
+    #[test]
+    fn it_streams_the_same_events_it_reads() -> Result<()> {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/100715.fcs");
+        let values = match File::open(&path)?.read_fcs()?.data {
+            Data::Float(values) => values,
+            other => panic!("unexpected data: {:?}", other),
+        };
+
+        let mut file = File::open(&path)?;
+        let mut stream = file.stream_events()?;
+        assert_eq!(stream.remaining(), 65016);
+
+        let first = stream.next().unwrap()?;
+        assert_eq!(first.len(), 16);
+        assert_eq!(first[0].as_f64(), values[0] as f64);
+        let second = stream.next_chunk(0).unwrap()?;
+        assert_eq!(second.len(), 16);
+        assert_eq!(second[0].as_f64(), values[16] as f64);
+
+        let mut streamed = 2;
+        while let Some(chunk) = stream.next_chunk(1000) {
+            let chunk = chunk?;
+            for (i, value) in chunk.iter().enumerate() {
+                assert_eq!(value.as_f64(), values[streamed * 16 + i] as f64);
+            }
+            streamed += chunk.len() / 16;
+        }
+        assert_eq!(streamed, 65016);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_rejects_data_outside_the_source() {
+        assert!(matches!(
+            declaring_data_to(99999).stream_events(),
+            Err(FcsError::TruncatedSegment {
+                offset: 256,
+                expected: 99744,
+                found: 3840
+            })
+        ));
+        assert!(matches!(
+            declaring_data_to(u64::MAX).stream_events(),
+            Err(FcsError::InvalidSegment { .. })
+        ));
+    }
+}
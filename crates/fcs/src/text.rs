@@ -1,33 +1,39 @@
 use std::{
-    collections::BTreeMap,
-    io::{self, Cursor, Read},
+    collections::{BTreeMap, BTreeSet},
     ops::Range,
+    str::FromStr,
+    time::Duration,
 };
 
 use crate::{
-    data::{Byteord, DataType},
-    keywords::{OptionalKeyword, RequiredKeyword},
+    compensation::SpilloverMatrix,
+    data::{Byteord, DataType, Mode},
+    date::{duration, Date, DateTime, Time},
+    error::{FcsError, Result},
+    keywords::{describes_data, is_standard, OptionalKeyword, ParameterKeyword, RequiredKeyword},
 };
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Text {
     pub pairs: BTreeMap<String, String>,
+    /// Keywords in `pairs` that were read from the supplemental TEXT segment.
+    pub supplemental: BTreeSet<String>,
 }
 
 #[derive(Debug)]
 pub struct Parameter {
     /// index
     pub index: u32,
-    /// $PnB
-    pub bits: u32,
+    /// $PnB - None when `*`, marking delimited ASCII values
+    pub bits: Option<u32>,
     /// $PnE - should not be optional
     pub amplyficatioin_type: Option<String>,
     /// $PnN
     pub short_name: String,
     /// $PnR
-    pub range: Range<u32>,
+    pub range: Range<u64>,
     /// $PnD
-    pub visualization_scale: Option<u32>,
+    pub visualization_scale: Option<String>,
     /// $PnF
     pub optical_filter_name: Option<String>,
     /// $PnG
@@ -44,6 +50,20 @@ pub struct Parameter {
     pub detector_type: Option<String>,
     /// $PnV
     pub detector_voltage: Option<String>,
+    /// $PnDATATYPE - overrides $DATATYPE for this parameter (FCS 3.2)
+    pub data_type: Option<DataType>,
+    /// $PnDET (FCS 3.2)
+    pub detector_name: Option<String>,
+    /// $PnTYPE (FCS 3.2)
+    pub parameter_type: Option<String>,
+    /// $PnFEATURE (FCS 3.2)
+    pub feature: Option<String>,
+    /// $PnANALYTE (FCS 3.2)
+    pub analyte: Option<String>,
+    /// $PnTAG (FCS 3.2)
+    pub tag: Option<String>,
+    /// $PnCALIBRATION
+    pub calibration: Option<String>,
 }
 
 #[derive(Debug)]
@@ -52,9 +72,22 @@ pub struct Gate {
     pub index: u32,
     /// $GnS
     pub name: Option<String>,
+    /// $GnN
+    pub short_name: Option<String>,
+    /// $GnE
+    pub amplification_type: Option<String>,
+    /// $GnF
+    pub optical_filter_name: Option<String>,
+    /// $GnP
+    pub percent_emitted: Option<String>,
+    /// $GnR
+    pub range: Option<String>,
+    /// $GnT
+    pub detector_type: Option<String>,
+    /// $GnV
+    pub detector_voltage: Option<String>,
 }
 
-// TODO(@fdionisi): add dates and times
 #[derive(Debug)]
 pub struct Metadata {
     /// $OP
@@ -73,16 +106,62 @@ pub struct Metadata {
     pub source: Option<String>,
     /// $SYS
     pub system: Option<String>,
+    /// $CARRIERID (FCS 3.2)
+    pub carrier_id: Option<String>,
+    /// $CARRIERTYPE (FCS 3.2)
+    pub carrier_type: Option<String>,
+    /// $LOCATIONID (FCS 3.2)
+    pub location_id: Option<String>,
+    /// $FLOWRATE (FCS 3.2)
+    pub flowrate: Option<String>,
+    /// $UNSTAINEDCENTERS (FCS 3.2)
+    pub unstained_centers: Option<String>,
+    /// $UNSTAINEDINFO (FCS 3.2)
+    pub unstained_info: Option<String>,
+    /// $DATE
+    pub date: Option<Date>,
+    /// $BTIM
+    pub begin_time: Option<Time>,
+    /// $ETIM
+    pub end_time: Option<Time>,
+    /// $LAST_MODIFIED
+    pub last_modified: Option<DateTime>,
+    /// Time from $BTIM to $ETIM
+    pub acquisition_duration: Option<Duration>,
 }
 
 impl Text {
-    pub fn new(bytes: &Vec<u8>) -> io::Result<Self> {
-        let mut rdr = Cursor::new(bytes);
-        let mut buf = String::new();
-        rdr.read_to_string(&mut buf)?;
-
+    pub fn new(bytes: &[u8]) -> Result<Self> {
         Ok(Text {
-            pairs: parse_pairs(&buf),
+            pairs: parse_pairs(bytes)?,
+            supplemental: BTreeSet::new(),
+        })
+    }
+
+    /// Merge the keywords of the supplemental TEXT segment.
+    ///
+    /// Keywords already present in the primary TEXT take precedence.
+    pub fn merge_supplemental(&mut self, bytes: &[u8]) -> Result<()> {
+        for (keyword, value) in parse_pairs(bytes)? {
+            if !self.pairs.contains_key(&keyword) {
+                self.supplemental.insert(keyword.clone());
+                self.pairs.insert(keyword, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Offsets of the supplemental TEXT segment, if the file has one.
+    pub fn supplemental_offsets(&self) -> Result<Option<(u64, u64)>> {
+        let start: u64 = self
+            .parse_optional(RequiredKeyword::BeginsText)?
+            .unwrap_or(0);
+        let end: u64 = self.parse_optional(RequiredKeyword::EndsText)?.unwrap_or(0);
+
+        Ok(match (start, end) {
+            (0, 0) => None,
+            offsets => Some(offsets),
         })
     }
 
@@ -93,6 +172,113 @@ impl Text {
         self.pairs.get(&key.to_string())
     }
 
+    /// Set the value of a keyword, returning the previous one.
+    ///
+    /// Keywords describing the layout of DATA, and `$` keywords the standard
+    /// does not define, are rejected with [`FcsError::ReservedKeyword`]. A
+    /// keyword keeps the segment, primary or supplemental, it was read from.
+    pub fn set<K, V>(&mut self, keyword: K, value: V) -> Result<Option<String>>
+    where
+        K: ToString,
+        V: Into<String>,
+    {
+        let keyword = keyword.to_string();
+        let value = value.into();
+        if keyword.is_empty() || value.is_empty() {
+            return Err(FcsError::InvalidKeyword { keyword, value });
+        }
+        editable(&keyword)?;
+
+        Ok(self.pairs.insert(keyword, value))
+    }
+
+    /// Remove a keyword, returning its value.
+    ///
+    /// Besides those [`Text::set`] rejects, $PnN and $PnE cannot be removed
+    /// as every parameter requires them.
+    pub fn remove<K>(&mut self, keyword: K) -> Result<Option<String>>
+    where
+        K: ToString,
+    {
+        let keyword = keyword.to_string();
+        editable(&keyword)?;
+        if keyword.strip_prefix("$P").is_some_and(|rest| {
+            let suffix = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+            suffix.len() < rest.len() && ["N", "E"].contains(&suffix.to_ascii_uppercase().as_str())
+        }) {
+            return Err(FcsError::ReservedKeyword(keyword));
+        }
+
+        self.supplemental.remove(&keyword);
+        Ok(self.pairs.remove(&keyword))
+    }
+
+    /// Move the value of a keyword to a new one that is not yet present.
+    pub fn rename<K, N>(&mut self, keyword: K, name: N) -> Result<()>
+    where
+        K: ToString,
+        N: ToString,
+    {
+        let keyword = keyword.to_string();
+        let name = name.to_string();
+        if let Some(value) = self.pairs.get(&name) {
+            return Err(FcsError::InvalidKeyword {
+                keyword: name,
+                value: value.clone(),
+            });
+        }
+
+        let supplemental = self.supplemental.contains(&keyword);
+        let value = self
+            .remove(&keyword)?
+            .ok_or(FcsError::MissingKeyword(keyword.clone()))?;
+        if let Err(err) = self.set(&name, value.clone()) {
+            // Put the original back so a failed rename changes nothing.
+            self.pairs.insert(keyword.clone(), value);
+            if supplemental {
+                self.supplemental.insert(keyword);
+            }
+            return Err(err);
+        }
+        if supplemental {
+            self.supplemental.insert(name);
+        }
+
+        Ok(())
+    }
+
+    /// Value of a keyword the standard requires to be present.
+    pub fn required<K>(&self, key: K) -> Result<&String>
+    where
+        K: ToString,
+    {
+        let key = key.to_string();
+        self.pairs.get(&key).ok_or(FcsError::MissingKeyword(key))
+    }
+
+    /// Parse the value of a required keyword.
+    pub fn parse_required<K, T>(&self, key: K) -> Result<T>
+    where
+        K: ToString,
+        T: FromStr,
+    {
+        let key = key.to_string();
+        let value = self.required(&key)?;
+        parse_value(&key, value)
+    }
+
+    /// Parse the value of an optional keyword, if present.
+    pub fn parse_optional<K, T>(&self, key: K) -> Result<Option<T>>
+    where
+        K: ToString,
+        T: FromStr,
+    {
+        let key = key.to_string();
+        self.get(&key)
+            .map(|value| parse_value(&key, value))
+            .transpose()
+    }
+
     pub fn metadata(&self) -> Metadata {
         Metadata {
             operator: self.get(OptionalKeyword::Op).map(|s| s.to_owned()),
@@ -103,99 +289,420 @@ impl Text {
             institute: self.get(OptionalKeyword::Inst).map(|s| s.to_owned()),
             source: self.get(OptionalKeyword::Src).map(|s| s.to_owned()),
             system: self.get(OptionalKeyword::Sys).map(|s| s.to_owned()),
+            carrier_id: self.get(OptionalKeyword::CarrierId).map(|s| s.to_owned()),
+            carrier_type: self.get(OptionalKeyword::CarrierType).map(|s| s.to_owned()),
+            location_id: self.get(OptionalKeyword::LocationId).map(|s| s.to_owned()),
+            flowrate: self.get(OptionalKeyword::Flowrate).map(|s| s.to_owned()),
+            unstained_centers: self
+                .get(OptionalKeyword::UnstainedCenters)
+                .map(|s| s.to_owned()),
+            unstained_info: self
+                .get(OptionalKeyword::UnstainedInfo)
+                .map(|s| s.to_owned()),
+            // Values that cannot be parsed are left out; the typed
+            // accessors report them.
+            date: self.date().ok().flatten(),
+            begin_time: self.begin_time().ok().flatten(),
+            end_time: self.end_time().ok().flatten(),
+            last_modified: self.last_modified().ok().flatten(),
+            acquisition_duration: self.acquisition_duration().ok().flatten(),
         }
     }
 
-    pub fn total_events(&self) -> u32 {
-        self.get(RequiredKeyword::Tot)
-            .expect("$TOT is invalid")
-            .parse()
-            .expect("$TOT to be valid number")
+    /// $DATE: date of acquisition.
+    pub fn date(&self) -> Result<Option<Date>> {
+        self.parse_with(OptionalKeyword::Date, Date::parse)
     }
 
-    pub fn gates_number(&self) -> Option<u32> {
-        self.get(OptionalKeyword::Gate)
-            .map(|g| g.parse().expect("$GATE to be valid number"))
+    /// $BTIM: clock time at the beginning of acquisition.
+    pub fn begin_time(&self) -> Result<Option<Time>> {
+        self.parse_with(OptionalKeyword::Btim, Time::parse)
     }
 
-    pub fn gates(&self) -> Vec<Gate> {
-        (1..self.gates_number().unwrap_or(0) + 1)
-            .map(|index| Gate {
-                index,
-                name: self
-                    .pairs
-                    .get(&format!("$G{}S", index))
-                    .map(|s| s.to_owned()),
+    /// $ETIM: clock time at the end of acquisition.
+    pub fn end_time(&self) -> Result<Option<Time>> {
+        self.parse_with(OptionalKeyword::Etim, Time::parse)
+    }
+
+    /// $LAST_MODIFIED: when the file was last modified.
+    pub fn last_modified(&self) -> Result<Option<DateTime>> {
+        self.parse_with(OptionalKeyword::LastModified, DateTime::parse)
+    }
+
+    /// Time from $BTIM to $ETIM, if both are present.
+    pub fn acquisition_duration(&self) -> Result<Option<Duration>> {
+        Ok(match (self.begin_time()?, self.end_time()?) {
+            (Some(begin), Some(end)) => Some(duration(begin, end)),
+            _ => None,
+        })
+    }
+
+    /// Parse the value of an optional keyword whose format FromStr does not
+    /// describe.
+    fn parse_with<K, T>(&self, key: K, parse: fn(&str) -> Option<T>) -> Result<Option<T>>
+    where
+        K: ToString,
+    {
+        let key = key.to_string();
+        self.get(&key)
+            .map(|value| {
+                parse(value).ok_or_else(|| FcsError::InvalidKeyword {
+                    keyword: key.clone(),
+                    value: value.clone(),
+                })
             })
-            .collect()
+            .transpose()
+    }
+
+    pub fn total_events(&self) -> Result<u32> {
+        self.parse_required(RequiredKeyword::Tot)
+    }
+
+    pub fn gates_number(&self) -> Result<Option<u32>> {
+        self.parse_optional(OptionalKeyword::Gate)
+    }
+
+    pub fn gates(&self) -> Result<Vec<Gate>> {
+        gates(&self.pairs)
     }
 
     /// $PAR: Number of parameters in an event.
-    pub fn parameters_number(&self) -> u32 {
-        self.get(RequiredKeyword::Par)
-            .expect("$PAR is invalid")
-            .parse()
-            .expect("$PAR to be valid number")
-    }
-
-    pub fn parameters(&self) -> Vec<Parameter> {
-        (1..(self.parameters_number() + 1))
-            .map(|i| Parameter {
-                index: i,
-                bits: self
-                    .get(&format!("$P{}B", i))
-                    .expect("to have value")
-                    .parse()
-                    .expect("to be u32"),
-                amplyficatioin_type: self.pairs.get(&format!("$P{}E", i)).map(|s| s.to_owned()),
-                short_name: self
-                    .get(&format!("$P{}N", i))
-                    .expect("to have value")
-                    .to_owned(),
-                range: 0..self
-                    .get(&format!("$P{}R", i))
-                    .expect("to have value")
-                    .parse()
-                    .expect("to be a u32"),
-                visualization_scale: self
-                    .get(&format!("$P{}R", i))
-                    .map(|s| s.parse().expect("to be a u32")),
-                optical_filter_name: self.pairs.get(&format!("$P{}F", i)).map(|s| s.to_owned()),
-                amplifier_gain: self
-                    .get(&format!("$P{}G", i))
-                    .map(|s| s.parse().expect("to be u32")),
-                excitation_wavelengths: self.get(&format!("$P{}L", i)).map(|s| s.to_owned()),
-                excitation_power: self.get(&format!("$P{}O", i)).map(|s| s.to_owned()),
-                emitted_light_collected: self.get(&format!("$P{}P", i)).map(|s| s.to_owned()),
-                name: self.get(&format!("$P{}S", i)).map(|s| s.to_owned()),
-                detector_type: self.get(&format!("$P{}T", i)).map(|s| s.to_owned()),
-                detector_voltage: self.get(&format!("$P{}V", i)).map(|s| s.to_owned()),
+    pub fn parameters_number(&self) -> Result<u32> {
+        self.parse_required(RequiredKeyword::Par)
+    }
+
+    pub fn parameters(&self) -> Result<Vec<Parameter>> {
+        (1..=self.parameters_number()?)
+            .map(|i| {
+                let get = |keyword: ParameterKeyword| self.get(keyword.of(i)).map(|s| s.to_owned());
+
+                Ok(Parameter {
+                    index: i,
+                    bits: match self.required(ParameterKeyword::Bits.of(i))?.trim() {
+                        "*" => None,
+                        _ => Some(self.parse_required(ParameterKeyword::Bits.of(i))?),
+                    },
+                    amplyficatioin_type: get(ParameterKeyword::Amplification),
+                    short_name: self.required(ParameterKeyword::ShortName.of(i))?.to_owned(),
+                    range: 0..self.parse_range(i)?,
+                    visualization_scale: get(ParameterKeyword::Display),
+                    optical_filter_name: get(ParameterKeyword::Filter),
+                    amplifier_gain: self.parse_optional(ParameterKeyword::Gain.of(i))?,
+                    excitation_wavelengths: get(ParameterKeyword::ExcitationWavelength),
+                    excitation_power: get(ParameterKeyword::ExcitationPower),
+                    emitted_light_collected: get(ParameterKeyword::EmittedLight),
+                    name: get(ParameterKeyword::LongName),
+                    detector_type: get(ParameterKeyword::DetectorType),
+                    detector_voltage: get(ParameterKeyword::DetectorVoltage),
+                    data_type: self.parse_optional(ParameterKeyword::DataType.of(i))?,
+                    detector_name: get(ParameterKeyword::Detector),
+                    parameter_type: get(ParameterKeyword::Type),
+                    feature: get(ParameterKeyword::Feature),
+                    analyte: get(ParameterKeyword::Analyte),
+                    tag: get(ParameterKeyword::Tag),
+                    calibration: get(ParameterKeyword::Calibration),
+                })
             })
             .collect()
     }
 
+    /// $PnR, which FCS 3.1 allows to be written as a floating point value.
+    fn parse_range(&self, index: u32) -> Result<u64> {
+        let keyword = ParameterKeyword::Range.of(index);
+        let value = self.required(&keyword)?;
+
+        value.trim().parse::<u64>().or_else(|_| {
+            parse_value::<f64>(&keyword, value)
+                .ok()
+                .filter(|range| *range >= 0.0)
+                .map(|range| range.ceil() as u64)
+                .ok_or_else(|| FcsError::InvalidKeyword {
+                    keyword: keyword.clone(),
+                    value: value.to_owned(),
+                })
+        })
+    }
+
     /// Type of data in DATA segment (ASCII, integer, floating point).
-    pub fn data_type(&self) -> DataType {
-        self.get(RequiredKeyword::DataType)
-            .expect("$DATATYPE is invalid")
-            .into()
+    pub fn data_type(&self) -> Result<DataType> {
+        self.required(RequiredKeyword::DataType)?.parse()
+    }
+
+    /// Data mode, list mode when $MODE is absent as FCS 3.2 deprecates it.
+    pub fn mode(&self) -> Result<Mode> {
+        self.get(RequiredKeyword::Mode)
+            .map_or(Ok(Mode::List), |mode| mode.parse())
+    }
+
+    /// Fail unless DATA holds list mode events rather than histograms.
+    pub(crate) fn require_list_mode(&self) -> Result<()> {
+        match self.mode()? {
+            Mode::List => Ok(()),
+            _ => Err(FcsError::InvalidKeyword {
+                keyword: RequiredKeyword::Mode.to_string(),
+                value: self.required(RequiredKeyword::Mode)?.clone(),
+            }),
+        }
+    }
+
+    /// Spillover matrix from $SPILLOVER, SPILL, $COMP or $DFCiTOj.
+    pub fn spillover(&self) -> Result<Option<SpilloverMatrix>> {
+        SpilloverMatrix::from_text(self)
     }
 
     /// Byte order for data acquisition computer.
-    pub fn byteord(&self) -> Byteord {
-        self.get(RequiredKeyword::Byteord)
-            .expect("$BYTEORD is invalid")
-            .into()
+    pub fn byteord(&self) -> Result<Byteord> {
+        self.required(RequiredKeyword::Byteord)?.parse()
+    }
+}
+
+/// Gate parameters described by $GATE and the $Gn* keywords.
+pub(crate) fn gates(pairs: &BTreeMap<String, String>) -> Result<Vec<Gate>> {
+    let keyword = OptionalKeyword::Gate.to_string();
+    let number: u32 = pairs
+        .get(&keyword)
+        .map(|value| parse_value(&keyword, value))
+        .transpose()?
+        .unwrap_or(0);
+
+    Ok((1..=number)
+        .map(|index| {
+            let get = |suffix: &str| pairs.get(&format!("$G{}{}", index, suffix)).cloned();
+
+            Gate {
+                index,
+                name: get("S"),
+                short_name: get("N"),
+                amplification_type: get("E"),
+                optical_filter_name: get("F"),
+                percent_emitted: get("P"),
+                range: get("R"),
+                detector_type: get("T"),
+                detector_voltage: get("V"),
+            }
+        })
+        .collect())
+}
+
+/// Fail unless the keyword can be edited without touching DATA.
+pub(crate) fn editable(keyword: &str) -> Result<()> {
+    if keyword.starts_with('$') && (!is_standard(keyword) || describes_data(keyword)) {
+        return Err(FcsError::ReservedKeyword(keyword.to_string()));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn parse_value<T>(keyword: &str, value: &str) -> Result<T>
+where
+    T: FromStr,
+{
+    value.trim().parse().map_err(|_| FcsError::InvalidKeyword {
+        keyword: keyword.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// Parse keyword/value pairs, using the first byte as delimiter.
+///
+/// A doubled delimiter stands for the delimiter character itself inside a
+/// keyword or value, except after a keyword when a `$` keyword or the end
+/// of TEXT follows: there it is an empty value, which FCS 3.0 and earlier
+/// allow.
+pub(crate) fn parse_pairs(bytes: &[u8]) -> Result<BTreeMap<String, String>> {
+    let delimiter = *bytes.first().ok_or(FcsError::InvalidText { offset: 0 })?;
+
+    let mut tokens = vec![];
+    let mut token = vec![];
+    let mut start = 1;
+    let mut i = 1;
+    while i < bytes.len() {
+        if bytes[i] != delimiter {
+            token.push(bytes[i]);
+            i += 1;
+        } else if bytes.get(i + 1) == Some(&delimiter) {
+            let keyword = tokens.len() % 2 == 0;
+            if keyword && matches!(bytes.get(i + 2), None | Some(b'$')) {
+                tokens.push(decode_token(std::mem::take(&mut token), start)?);
+                tokens.push(String::new());
+                i += 2;
+                start = i;
+            } else {
+                token.push(delimiter);
+                i += 2;
+            }
+        } else {
+            tokens.push(decode_token(std::mem::take(&mut token), start)?);
+            i += 1;
+            start = i;
+        }
     }
+
+    // Some writers omit the closing delimiter or pad the segment with spaces.
+    if !token.iter().all(u8::is_ascii_whitespace) {
+        tokens.push(decode_token(token, start)?);
+    }
+
+    if tokens.len() % 2 != 0 {
+        return Err(FcsError::UnpairedKeyword(tokens.pop().unwrap_or_default()));
+    }
+
+    let mut tokens = tokens.into_iter();
+    let mut pairs = BTreeMap::new();
+    while let (Some(keyword), Some(value)) = (tokens.next(), tokens.next()) {
+        pairs.insert(keyword, value);
+    }
+
+    Ok(pairs)
 }
 
-fn parse_pairs(text: &str) -> BTreeMap<String, String> {
-    BTreeMap::from_iter(
-        text.trim_start_matches("\u{005C}")
-            .trim_end_matches("\u{005C}")
-            .split("\u{005C}")
-            .collect::<Vec<&str>>()
-            .chunks(2)
-            .map(|chunk| (chunk[0].into(), chunk[1].into())),
-    )
+fn decode_token(token: Vec<u8>, start: usize) -> Result<String> {
+    String::from_utf8(token).map_err(|err| FcsError::InvalidText {
+        offset: (start + err.utf8_error().valid_up_to()) as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::error::FcsError;
+
+    use super::Text;
+
+    #[test]
+    fn it_reports_a_missing_keyword() {
+        let text = Text::new(b"\\$PAR\\2\\").unwrap();
+
+        match text.total_events() {
+            Err(FcsError::MissingKeyword(keyword)) => assert_eq!(keyword, "$TOT"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_reports_an_invalid_keyword_value() {
+        let text = Text::new(b"\\$PAR\\1\\$P1B\\sixteen\\$P1N\\FSC-A\\$P1R\\1024\\").unwrap();
+
+        match text.parameters() {
+            Err(FcsError::InvalidKeyword { keyword, value }) => {
+                assert_eq!(keyword, "$P1B");
+                assert_eq!(value, "sixteen");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_reports_missing_parameters_of_the_largest_par() {
+        let text = Text::new(b"\\$PAR\\4294967295\\").unwrap();
+
+        match text.parameters() {
+            Err(FcsError::MissingKeyword(keyword)) => assert_eq!(keyword, "$P1B"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_reports_an_unsupported_data_type() {
+        let text = Text::new(b"\\$DATATYPE\\X\\").unwrap();
+
+        match text.data_type() {
+            Err(FcsError::UnsupportedDataType(data_type)) => assert_eq!(data_type, "X"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_reads_the_delimiter_from_the_first_byte() {
+        let text = Text::new(b"|$TOT|10|$FIL|a\\b.fcs|").unwrap();
+
+        assert_eq!(text.total_events().unwrap(), 10);
+        assert_eq!(text.get("$FIL").unwrap(), "a\\b.fcs");
+    }
+
+    #[test]
+    fn it_unescapes_doubled_delimiters() {
+        let text = Text::new(b"/$FIL/data//2007//a.fcs/$SRC/x//y/").unwrap();
+
+        assert_eq!(text.get("$FIL").unwrap(), "data/2007/a.fcs");
+        assert_eq!(text.get("$SRC").unwrap(), "x/y");
+    }
+
+    #[test]
+    fn it_reads_empty_values() {
+        let text = Text::new(b"\\$COM\\\\$FIL\\a.fcs\\$SRC\\\\").unwrap();
+
+        assert_eq!(text.get("$COM").unwrap(), "");
+        assert_eq!(text.get("$FIL").unwrap(), "a.fcs");
+        assert_eq!(text.get("$SRC").unwrap(), "");
+        assert_eq!(text.pairs.len(), 3);
+    }
+
+    #[test]
+    fn it_reports_an_unpaired_keyword() {
+        match Text::new(b"/$TOT/10/$PAR/") {
+            Err(FcsError::UnpairedKeyword(keyword)) => assert_eq!(keyword, "$PAR"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_merges_supplemental_keywords() {
+        let mut text = Text::new(b"/$TOT/10/$FIL/a.fcs/").unwrap();
+
+        text.merge_supplemental(b"/$FIL/b.fcs/$SPILLOVER/1,FL1-A,1/")
+            .unwrap();
+
+        assert_eq!(text.get("$FIL").unwrap(), "a.fcs");
+        assert_eq!(text.get("$SPILLOVER").unwrap(), "1,FL1-A,1");
+        assert_eq!(
+            text.supplemental.iter().collect::<Vec<_>>(),
+            vec!["$SPILLOVER"]
+        );
+    }
+
+    #[test]
+    fn it_rejects_edits_to_reserved_keywords() {
+        let mut text = Text::new(b"\\$PAR\\1\\$P1N\\FSC-H\\$P1B\\16\\").unwrap();
+
+        for keyword in ["$PAR", "$P1B", "$BEGINDATA", "$FOO"] {
+            assert!(matches!(
+                text.set(keyword, "1"),
+                Err(FcsError::ReservedKeyword(_))
+            ));
+        }
+        assert!(matches!(
+            text.remove("$P1N"),
+            Err(FcsError::ReservedKeyword(_))
+        ));
+        assert!(matches!(
+            text.rename("$P1N", "$P1S"),
+            Err(FcsError::ReservedKeyword(_))
+        ));
+
+        text.set("$P1S", "CD3").unwrap();
+        text.rename("$P1S", "LABEL").unwrap();
+        assert_eq!(text.get("LABEL").unwrap(), "CD3");
+        assert_eq!(text.get("$P1S"), None);
+    }
+
+    #[test]
+    fn it_parses_dates_and_times() {
+        let text = Text::new(b"\\$DATE\\17-JUL-07\\$BTIM\\15:36:28:30\\$ETIM\\15:38:06\\").unwrap();
+        let metadata = text.metadata();
+
+        assert_eq!(metadata.date.unwrap().to_string(), "17-JUL-2007");
+        assert_eq!(
+            metadata.acquisition_duration,
+            Some(Duration::from_millis(97_500))
+        );
+        assert_eq!(metadata.last_modified, None);
+
+        let text = Text::new(b"\\$BTIM\\3pm\\").unwrap();
+        assert!(matches!(
+            text.begin_time(),
+            Err(FcsError::InvalidKeyword { .. })
+        ));
+    }
 }
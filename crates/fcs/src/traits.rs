@@ -1,13 +1,20 @@
 use std::{
-    io::{self, BufReader, Read, Seek, SeekFrom, Write},
-    path::Path,
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    mem,
 };
 
 use crate::{
-    data::Data,
-    fcs::Fcs,
+    analysis::Analysis,
+    crc::{Crc, Crc16},
+    data::{Data, Mode},
+    error::{FcsError, Result},
+    fcs::{Fcs, FcsMetadata},
     header::Header,
+    histogram::Histogram,
+    keywords::RequiredKeyword,
+    stream::EventStream,
     text::{Gate, Metadata, Parameter, Text},
+    writer::write_segments,
 };
 
 pub trait Meta {
@@ -27,43 +34,388 @@ pub trait Gating {
 }
 
 pub trait ByteRead: Read + Seek {
-    /// Read in bytes from start to stop inclusive.
-    fn read_bytes(&mut self, offset: u64, start: u64, stop: u64) -> io::Result<Vec<u8>> {
+    /// Read in bytes from start to stop inclusive, both relative to offset.
+    fn read_bytes(&mut self, offset: u64, start: u64, stop: u64) -> Result<Vec<u8>> {
+        let length = self.seek(SeekFrom::End(0))?;
+        let (first_byte, bytes_to_read) = segment_range(offset, start, stop, length)?;
+
         let mut f = BufReader::new(self);
-        let first_byte = offset + start;
         f.seek(SeekFrom::Start(first_byte))?;
 
-        let bytes_to_read = stop - first_byte + 1;
-        let mut result = vec![0; bytes_to_read as usize];
+        // Grow with what is actually read rather than trust the declared size.
+        let mut result = vec![];
+        f.take(bytes_to_read).read_to_end(&mut result)?;
 
-        let mut take = f.take(bytes_to_read);
-        take.read(&mut result)?;
+        if (result.len() as u64) < bytes_to_read {
+            return Err(FcsError::TruncatedSegment {
+                offset: first_byte,
+                expected: bytes_to_read,
+                found: result.len() as u64,
+            });
+        }
 
         Ok(result)
     }
 }
 
+/// First byte and length of the bytes from start to stop inclusive, both
+/// relative to offset, in a source of `length` bytes. The offsets come from
+/// the file, so overflows and segments past its end are errors.
+pub(crate) fn segment_range(offset: u64, start: u64, stop: u64, length: u64) -> Result<(u64, u64)> {
+    let invalid = || FcsError::InvalidSegment {
+        start: offset.saturating_add(start),
+        end: offset.saturating_add(stop),
+    };
+    if stop < start {
+        return Err(invalid());
+    }
+
+    let first_byte = offset.checked_add(start).ok_or_else(invalid)?;
+    let bytes_to_read = (stop - start).checked_add(1).ok_or_else(invalid)?;
+    let end = first_byte.checked_add(bytes_to_read).ok_or_else(invalid)?;
+    if end > length {
+        return Err(FcsError::TruncatedSegment {
+            offset: first_byte,
+            expected: bytes_to_read,
+            found: length.saturating_sub(first_byte),
+        });
+    }
+
+    Ok((first_byte, bytes_to_read))
+}
+
 pub trait FcsRead: ByteRead + Sized {
-    fn read_fcs(&mut self) -> io::Result<Fcs> {
-        let header = Header::new(self)?;
+    /// Read the first data set in the file.
+    fn read_fcs(&mut self) -> Result<Fcs> {
+        self.read_fcs_at(0)
+    }
 
-        let text = Text::new(&self.read_bytes(0, header.text_start, header.text_end)?)?;
+    /// Read the data set whose HEADER starts at offset.
+    fn read_fcs_at(&mut self, offset: u64) -> Result<Fcs> {
+        let FcsMetadata {
+            header,
+            text,
+            analysis,
+        } = self.read_metadata_at(offset)?;
 
-        let data = Data::new(
-            &self.read_bytes(0, header.data_start, header.data_end)?,
-            text.data_type(),
-            text.byteord(),
-        )?;
+        let bytes = match (header.data_start, header.data_end) {
+            (0, 0) => vec![],
+            (start, end) => self.read_bytes(offset, start, end)?,
+        };
+        let data = decode_data(&text, &bytes)?;
 
-        Ok(Fcs { header, text, data })
+        Ok(Fcs {
+            header,
+            text,
+            data,
+            analysis,
+            compensation: None,
+        })
     }
-}
 
-pub trait FcsWrite: Write {
-    fn write_fcs<P>(&mut self, _fcs: &Fcs) -> io::Result<()>
+    /// Read HEADER, TEXT and ANALYSIS of the first data set, leaving DATA
+    /// unread.
+    fn read_metadata(&mut self) -> Result<FcsMetadata> {
+        self.read_metadata_at(0)
+    }
+
+    /// Read HEADER, TEXT and ANALYSIS of the data set whose HEADER starts
+    /// at offset, leaving DATA unread.
+    fn read_metadata_at(&mut self, offset: u64) -> Result<FcsMetadata> {
+        let header = Header::new(self, offset)?;
+        read_segments(self, MetadataReader::new(header, true))
+    }
+
+    /// Check the CRC stored after the last segment of a data set read from
+    /// this source, failing with [`FcsError::CrcMismatch`] on corruption.
+    /// Versions before 3.0 define no CRC, so whatever follows is ignored.
+    fn verify_crc(&mut self, fcs: &Fcs) -> Result<Crc> {
+        if fcs.header.version < 3.0 {
+            return Ok(Crc::Absent);
+        }
+
+        let offset = fcs.header.offset;
+        let length = fcs.last_byte()? + 1;
+
+        let mut f = BufReader::new(self);
+        f.seek(SeekFrom::Start(offset))?;
+
+        let mut crc = Crc16::default();
+        let mut buf = [0; 64 * 1024];
+        let mut remaining = length;
+        while remaining > 0 {
+            let size = remaining.min(buf.len() as u64) as usize;
+            let chunk = &mut buf[..size];
+            f.read_exact(chunk)
+                .map_err(|_| FcsError::TruncatedSegment {
+                    offset,
+                    expected: length,
+                    found: length - remaining,
+                })?;
+            crc.update(chunk);
+            remaining -= chunk.len() as u64;
+        }
+
+        let mut stored = Vec::with_capacity(8);
+        f.take(8).read_to_end(&mut stored)?;
+        if stored.is_empty() {
+            return Ok(Crc::Absent);
+        }
+
+        let stored = String::from_utf8_lossy(&stored);
+        match stored.trim().parse::<u64>() {
+            Ok(0) if stored.len() == 8 => Ok(Crc::NotComputed),
+            Ok(value) if stored.len() == 8 && value <= u16::MAX as u64 => {
+                let stored = value as u16;
+                if stored == crc.finish() {
+                    Ok(Crc::Valid(stored))
+                } else {
+                    Err(FcsError::CrcMismatch {
+                        stored,
+                        computed: crc.finish(),
+                    })
+                }
+            }
+            _ => Err(FcsError::InvalidCrc(stored.into_owned())),
+        }
+    }
+
+    /// Write a data set read from this source with new TEXT and ANALYSIS
+    /// keywords, copying its DATA bytes verbatim and regenerating HEADER and
+    /// segment offsets. `header` is the one the data set was read with.
+    fn rewrite<W>(
+        &mut self,
+        header: &Header,
+        text: &Text,
+        analysis: Option<&Analysis>,
+        writer: &mut W,
+    ) -> Result<()>
     where
-        P: AsRef<Path>,
+        W: Write + ?Sized,
     {
-        Ok(())
+        let data = match (header.data_start, header.data_end) {
+            (0, 0) => vec![],
+            (start, end) => self.read_bytes(header.offset, start, end)?,
+        };
+
+        write_segments(writer, text, &data, analysis)
+    }
+
+    /// Stream the list mode events of the first data set without reading
+    /// the whole DATA segment in memory.
+    fn stream_events(&mut self) -> Result<EventStream<'_, Self>> {
+        self.stream_events_at(0)
+    }
+
+    /// Stream the list mode events of the data set whose HEADER starts at
+    /// offset.
+    fn stream_events_at(&mut self, offset: u64) -> Result<EventStream<'_, Self>> {
+        EventStream::new(self, offset)
+    }
+
+    /// Iterate every data set in the file by following $NEXTDATA.
+    fn datasets(&mut self) -> Datasets<'_, Self> {
+        Datasets {
+            reader: self,
+            offset: Some(0),
+        }
+    }
+
+    /// Read every data set in the file.
+    fn read_fcs_all(&mut self) -> Result<Vec<Fcs>> {
+        self.datasets().collect()
+    }
+}
+
+/// Read HEADER and TEXT, merging the supplemental TEXT and reconciling
+/// HEADER offsets with their keywords.
+pub(crate) fn read_text<R>(reader: &mut R, offset: u64) -> Result<(Header, Text)>
+where
+    R: ByteRead,
+{
+    let header = Header::new(reader, offset)?;
+    let metadata = read_segments(reader, MetadataReader::new(header, false))?;
+
+    Ok((metadata.header, metadata.text))
+}
+
+/// Drive a [`MetadataReader`] over a synchronous source.
+fn read_segments<R>(
+    reader: &mut R,
+    (mut segments, mut step): (MetadataReader, Step),
+) -> Result<FcsMetadata>
+where
+    R: ByteRead,
+{
+    loop {
+        step = match step {
+            Step::Read { start, stop } => {
+                segments.resume(&reader.read_bytes(segments.offset(), start, stop)?)?
+            }
+            Step::Done(metadata) => return Ok(metadata),
+        };
+    }
+}
+
+/// Next step in reading the metadata of a data set.
+pub(crate) enum Step {
+    /// Read bytes from start to stop inclusive, relative to the HEADER, and
+    /// pass them to [`MetadataReader::resume`].
+    Read {
+        start: u64,
+        stop: u64,
+    },
+    Done(FcsMetadata),
+}
+
+enum Stage {
+    Text,
+    Supplemental(Text),
+    Analysis(Text),
+}
+
+/// Reads TEXT, the supplemental TEXT and ANALYSIS following a HEADER,
+/// asking for each segment in turn so that sync and async sources take the
+/// same steps.
+pub(crate) struct MetadataReader {
+    header: Header,
+    analysis: bool,
+    stage: Stage,
+}
+
+impl MetadataReader {
+    /// Start after the HEADER, reading ANALYSIS too when `analysis` is set.
+    pub(crate) fn new(header: Header, analysis: bool) -> (Self, Step) {
+        let step = Step::Read {
+            start: header.text_start,
+            stop: header.text_end,
+        };
+        let reader = MetadataReader {
+            header,
+            analysis,
+            stage: Stage::Text,
+        };
+
+        (reader, step)
+    }
+
+    /// Offset of the HEADER in the source.
+    pub(crate) fn offset(&self) -> u64 {
+        self.header.offset
+    }
+
+    /// Take the bytes the last [`Step::Read`] asked for.
+    pub(crate) fn resume(&mut self, bytes: &[u8]) -> Result<Step> {
+        match mem::replace(&mut self.stage, Stage::Text) {
+            Stage::Text => {
+                let text = Text::new(bytes)?;
+                match text.supplemental_offsets()? {
+                    Some((start, stop)) => {
+                        self.stage = Stage::Supplemental(text);
+                        Ok(Step::Read { start, stop })
+                    }
+                    None => self.reconcile(text),
+                }
+            }
+            Stage::Supplemental(mut text) => {
+                text.merge_supplemental(bytes)?;
+                self.reconcile(text)
+            }
+            Stage::Analysis(text) => Ok(self.done(text, Some(Analysis::new(bytes)?))),
+        }
+    }
+
+    fn reconcile(&mut self, text: Text) -> Result<Step> {
+        self.header.reconcile(&text)?;
+
+        match (self.header.analysis_start, self.header.analysis_end) {
+            (Some(start), Some(stop)) if self.analysis => {
+                self.stage = Stage::Analysis(text);
+                Ok(Step::Read { start, stop })
+            }
+            _ => Ok(self.done(text, None)),
+        }
+    }
+
+    fn done(&mut self, text: Text, analysis: Option<Analysis>) -> Step {
+        Step::Done(FcsMetadata {
+            header: self.header.clone(),
+            text,
+            analysis,
+        })
+    }
+}
+
+/// Decode the DATA segment as TEXT describes it.
+pub(crate) fn decode_data(text: &Text, bytes: &[u8]) -> Result<Data> {
+    Ok(match text.mode()? {
+        Mode::List => Data::new(
+            bytes,
+            text.data_type()?,
+            text.byteord()?,
+            &text.parameters()?,
+        )?,
+        mode => Data::Histogram(Histogram::new(
+            bytes,
+            mode,
+            text.data_type()?,
+            text.byteord()?,
+            &text.parameters()?,
+        )?),
+    })
+}
+
+/// Iterator over the data sets chained in a file through $NEXTDATA.
+pub struct Datasets<'a, R> {
+    reader: &'a mut R,
+    offset: Option<u64>,
+}
+
+impl<R> Iterator for Datasets<'_, R>
+where
+    R: FcsRead,
+{
+    type Item = Result<Fcs>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset.take()?;
+        let fcs = match self.reader.read_fcs_at(offset) {
+            Ok(fcs) => fcs,
+            Err(err) => return Some(Err(err)),
+        };
+
+        // $NEXTDATA is relative to the current HEADER, so a valid chain only
+        // ever moves forward; this also rules out cycles.
+        let next = match fcs.text.parse_optional::<_, u64>(RequiredKeyword::NextData) {
+            Ok(next) => next.unwrap_or(0),
+            Err(err) => return Some(Err(err)),
+        };
+        if next != 0 {
+            match offset.checked_add(next) {
+                Some(next) => self.offset = Some(next),
+                None => {
+                    return Some(Err(FcsError::InvalidKeyword {
+                        keyword: RequiredKeyword::NextData.to_string(),
+                        value: next.to_string(),
+                    }))
+                }
+            }
+        }
+
+        Some(Ok(fcs))
+    }
+}
+
+pub trait FcsWrite: Write {
+    /// Write the data set as an FCS 3.1 file, or 3.2 when it uses
+    /// $PnDATATYPE, encoding DATA in the declared $DATATYPE and $BYTEORD.
+    fn write_fcs(&mut self, fcs: &Fcs) -> Result<()> {
+        let data = fcs.data.to_bytes(
+            fcs.text.data_type()?,
+            &fcs.text.byteord()?,
+            &fcs.text.parameters()?,
+        )?;
+
+        write_segments(self, &fcs.text, &data, fcs.analysis.as_ref())
     }
 }
@@ -0,0 +1,269 @@
+use std::{collections::BTreeMap, io::Write};
+
+use crate::{
+    analysis::Analysis,
+    crc::{footer, Crc16},
+    data::DataType,
+    error::{FcsError, Result},
+    header::HEADER_LENGTH,
+    keywords::{ParameterKeyword, RequiredKeyword},
+    text::Text,
+};
+
+/// Largest offset the 8 characters of a HEADER field can hold.
+const MAX_HEADER_OFFSET: u64 = 99_999_999;
+
+/// Preferred delimiters, before falling back to any other ASCII character.
+const DELIMITERS: &[u8] = b"|/\\!^~#;";
+
+/// Write a complete FCS 3.1 or 3.2 data set: HEADER, TEXT, supplemental
+/// TEXT, DATA, ANALYSIS and the trailing CRC.
+///
+/// Segment offsets are computed here, so any $BEGIN*/$END* and $NEXTDATA
+/// keywords in `text` are replaced.
+pub(crate) fn write_segments<W>(
+    writer: &mut W,
+    text: &Text,
+    data: &[u8],
+    analysis: Option<&Analysis>,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    let version = version(text)?;
+    let reserved = [
+        RequiredKeyword::BeginAnalysis,
+        RequiredKeyword::EndAnalysis,
+        RequiredKeyword::BeginData,
+        RequiredKeyword::EndData,
+        RequiredKeyword::BeginsText,
+        RequiredKeyword::EndsText,
+        RequiredKeyword::NextData,
+    ]
+    .map(|keyword| keyword.to_string());
+
+    let mut primary = BTreeMap::new();
+    let mut supplemental = BTreeMap::new();
+    for (keyword, value) in &text.pairs {
+        if reserved.contains(keyword) {
+            continue;
+        }
+        if text.supplemental.contains(keyword) {
+            supplemental.insert(keyword.clone(), value.clone());
+        } else {
+            primary.insert(keyword.clone(), value.clone());
+        }
+    }
+
+    let stext = match supplemental.is_empty() {
+        true => vec![],
+        false => serialize_pairs(&supplemental)?,
+    };
+    let analysis = match analysis {
+        Some(analysis) if !analysis.pairs.is_empty() => serialize_pairs(&analysis.pairs)?,
+        _ => vec![],
+    };
+
+    // Offsets are written inside TEXT, whose length depends on how many
+    // digits they take: iterate until they stop moving.
+    let mut layout = Layout::default();
+    let text = loop {
+        let mut pairs = primary.clone();
+        for (keyword, value) in [
+            (RequiredKeyword::BeginData, layout.data.0),
+            (RequiredKeyword::EndData, layout.data.1),
+            (RequiredKeyword::BeginAnalysis, layout.analysis.0),
+            (RequiredKeyword::EndAnalysis, layout.analysis.1),
+            (RequiredKeyword::BeginsText, layout.stext.0),
+            (RequiredKeyword::EndsText, layout.stext.1),
+            (RequiredKeyword::NextData, 0),
+        ] {
+            pairs.insert(keyword.to_string(), value.to_string());
+        }
+
+        let text = serialize_pairs(&pairs)?;
+        let next = Layout::new(text.len(), stext.len(), data.len(), analysis.len());
+        if next == layout {
+            break text;
+        }
+        layout = next;
+    };
+
+    if layout.text.1 > MAX_HEADER_OFFSET {
+        return Err(FcsError::InvalidSegment {
+            start: layout.text.0,
+            end: layout.text.1,
+        });
+    }
+
+    let (data_start, data_end) = match layout.data.1 > MAX_HEADER_OFFSET {
+        true => (0, 0),
+        false => layout.data,
+    };
+    let (analysis_start, analysis_end) = match layout.analysis.1 > MAX_HEADER_OFFSET {
+        true => (0, 0),
+        false => layout.analysis,
+    };
+    let header = format!(
+        "FCS{}    {:>8}{:>8}{:>8}{:>8}{:>8}{:>8}",
+        version, layout.text.0, layout.text.1, data_start, data_end, analysis_start, analysis_end,
+    );
+
+    let mut crc = Crc16::default();
+    for segment in [header.as_bytes(), &text, &stext, data, &analysis] {
+        crc.update(segment);
+        writer.write_all(segment)?;
+    }
+    writer.write_all(&footer(crc.finish()))?;
+
+    Ok(())
+}
+
+/// Offsets of each segment, first and last byte inclusive, or zeros for
+/// empty segments.
+#[derive(Default, PartialEq)]
+struct Layout {
+    text: (u64, u64),
+    stext: (u64, u64),
+    data: (u64, u64),
+    analysis: (u64, u64),
+}
+
+impl Layout {
+    fn new(text: usize, stext: usize, data: usize, analysis: usize) -> Self {
+        let mut next = HEADER_LENGTH;
+        let mut place = |length: usize| match length {
+            0 => (0, 0),
+            length => {
+                let start = next;
+                next += length as u64;
+                (start, next - 1)
+            }
+        };
+
+        Layout {
+            text: place(text),
+            stext: place(stext),
+            data: place(data),
+            analysis: place(analysis),
+        }
+    }
+}
+
+/// Serialize keyword/value pairs, choosing a delimiter that none of them
+/// start or end with and doubling it wherever it appears inside.
+/// Version to declare in HEADER: 3.2 when TEXT uses its $PnDATATYPE, 3.1
+/// otherwise. Both require every value to start on a byte boundary.
+fn version(text: &Text) -> Result<&'static str> {
+    let data_type = text.data_type()?;
+    let mut version = "3.1";
+    for parameter in text.parameters()? {
+        if parameter.data_type.is_some() {
+            version = "3.2";
+        }
+        match (parameter.data_type.unwrap_or(data_type), parameter.bits) {
+            (DataType::Ascii, _) | (_, None) => {}
+            (_, Some(bits)) if bits % 8 == 0 => {}
+            (_, Some(bits)) => {
+                return Err(FcsError::InvalidKeyword {
+                    keyword: ParameterKeyword::Bits.of(parameter.index),
+                    value: bits.to_string(),
+                })
+            }
+        }
+    }
+
+    Ok(version)
+}
+
+pub(crate) fn serialize_pairs(pairs: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+    for (keyword, value) in pairs {
+        if keyword.is_empty() || value.is_empty() {
+            return Err(FcsError::InvalidKeyword {
+                keyword: keyword.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    let tokens = || pairs.iter().flat_map(|(k, v)| [k.as_bytes(), v.as_bytes()]);
+    let candidates = || DELIMITERS.iter().copied().chain(1..=126);
+    let delimiter = candidates()
+        .find(|d| tokens().all(|token| !token.contains(d)))
+        .or_else(|| {
+            // A doubled delimiter inside a keyword could read as an empty
+            // value, so only values may hold it.
+            candidates().find(|d| {
+                pairs.keys().all(|keyword| !keyword.as_bytes().contains(d))
+                    && tokens().all(|token| token.first() != Some(d) && token.last() != Some(d))
+            })
+        })
+        .ok_or(FcsError::InvalidText { offset: 0 })?;
+
+    let mut bytes = vec![delimiter];
+    for token in tokens() {
+        for &byte in token {
+            bytes.push(byte);
+            if byte == delimiter {
+                bytes.push(delimiter);
+            }
+        }
+        bytes.push(delimiter);
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        builder::{FcsBuilder, ParameterDefinition},
+        data::DataType,
+        error::FcsError,
+        fcs::Fcs,
+        text::Text,
+        traits::FcsWrite,
+    };
+
+    use super::serialize_pairs;
+
+    #[test]
+    fn it_serializes_values_containing_common_delimiters() {
+        let candidates = "|/\\!^~#;";
+        let pairs = BTreeMap::from([
+            ("$COM".to_string(), format!("a{}b", candidates)),
+            ("$FIL".to_string(), "x.fcs".to_string()),
+        ]);
+
+        let bytes = serialize_pairs(&pairs).unwrap();
+        assert_eq!(Text::new(&bytes).unwrap().pairs, pairs);
+    }
+
+    #[test]
+    fn it_declares_the_version_the_parameters_need() {
+        let mut fcs = FcsBuilder::new(DataType::Float)
+            .parameter(ParameterDefinition::new("Time", 1024))
+            .parameter(ParameterDefinition::new("FSC-A", 1024))
+            .events([[1.0, 2.0]])
+            .build()
+            .unwrap();
+        let mut bytes = vec![];
+        bytes.write_fcs(&fcs).unwrap();
+        assert!(bytes.starts_with(b"FCS3.1"));
+
+        fcs.text.pairs.insert("$P1DATATYPE".into(), "F".into());
+        let mut bytes = vec![];
+        bytes.write_fcs(&fcs).unwrap();
+        assert!(bytes.starts_with(b"FCS3.2"));
+        assert_eq!(Fcs::from_bytes(&bytes).unwrap().header.version, 3.2);
+
+        fcs.text.pairs.insert("$P2B".into(), "12".into());
+        fcs.text.pairs.insert("$P2DATATYPE".into(), "I".into());
+        assert!(matches!(
+            vec![].write_fcs(&fcs),
+            Err(FcsError::InvalidKeyword { keyword, .. }) if keyword == "$P2B"
+        ));
+    }
+}